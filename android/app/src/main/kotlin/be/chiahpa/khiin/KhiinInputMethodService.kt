@@ -26,7 +26,9 @@ class KhiinInputMethodService : InputMethodService() {
     override fun onCreateInputView(): View {
         val decorView = window?.window?.decorView
         lifecycleOwner.attachToDecorView(decorView)
-        return ComposeKeyboardView(this, dbPath)
+        return ComposeKeyboardView(this, dbPath) { text ->
+            currentInputConnection?.commitText(text, 1)
+        }
     }
 
     override fun onStartInputView(info: EditorInfo?, restarting: Boolean) {
@@ -1,5 +1,6 @@
 package be.chiahpa.khiin.keyboard
 
+import androidx.compose.foundation.clickable
 import androidx.compose.foundation.layout.Arrangement
 import androidx.compose.foundation.layout.Row
 import androidx.compose.foundation.layout.fillMaxWidth
@@ -28,7 +29,13 @@ fun CandidatesBar(viewModel: KeyboardViewModel, height: Dp) {
             }
             is CandidateState.Loaded -> {
                 state.candidates.candidatesList.forEach {
-                    Text(it.value, fontSize = 28.sp)
+                    Text(
+                        it.value,
+                        fontSize = 28.sp,
+                        modifier = Modifier.clickable {
+                            viewModel.selectCandidate(it.id)
+                        }
+                    )
                 }
             }
         }
@@ -11,14 +11,15 @@ import be.chiahpa.khiin.utils.viewModelFactory
 @SuppressLint("ViewConstructor")
 class ComposeKeyboardView constructor(
     context: Context,
-    private val dbPath: String
+    private val dbPath: String,
+    private val onCommitText: (String) -> Unit
 ) :
     AbstractComposeView(context) {
     @Composable
     override fun Content() {
         val keyboardViewModel: KeyboardViewModel =
             viewModel(factory = viewModelFactory {
-                KeyboardViewModel(dbPath)
+                KeyboardViewModel(dbPath, onCommitText)
             })
 
         KhiinTheme {
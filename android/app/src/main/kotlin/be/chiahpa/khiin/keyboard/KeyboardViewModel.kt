@@ -6,10 +6,16 @@ import androidx.lifecycle.ViewModel
 import androidx.lifecycle.viewModelScope
 import be.chiahpa.khiin.EngineManager
 import be.chiahpa.khiin.utils.loggerFor
+import khiin.proto.AppInputMode
 import khiin.proto.CandidateList
 import khiin.proto.CommandType
+import khiin.proto.EditState
 import khiin.proto.Request
+import khiin.proto.Response
+import khiin.proto.appConfig
+import khiin.proto.boolValue
 import khiin.proto.keyEvent
+import khiin.proto.request
 import kotlinx.coroutines.flow.MutableStateFlow
 import kotlinx.coroutines.flow.asStateFlow
 import kotlinx.coroutines.launch
@@ -43,11 +49,25 @@ internal fun KeyCoordinateMap.keyAt(offset: Offset): KeyData? {
     return null
 }
 
-class KeyboardViewModel(dbPath: String) : ViewModel() {
+class KeyboardViewModel(
+    dbPath: String,
+    private val onCommitText: (String) -> Unit
+) : ViewModel() {
     init {
         EngineManager.startup(dbPath)
+        EngineManager.sendCommand(request {
+            type = CommandType.CMD_SET_CONFIG
+            config = appConfig {
+                // Every field of the config is applied, so keep the
+                // engine's default input mode
+                inputMode = AppInputMode.MANUAL
+                nextWordEnabled = boolValue { value = true }
+            }
+        })
     }
 
+    private var editState = EditState.ES_EMPTY
+
     override fun onCleared() {
         super.onCleared()
         EngineManager.shutdown()
@@ -77,16 +97,61 @@ class KeyboardViewModel(dbPath: String) : ViewModel() {
         }
 
         if (req.type != CommandType.CMD_UNSPECIFIED) {
-            viewModelScope.launch {
-                val res = EngineManager.sendCommand(req.build())
-                if (res.response.candidateList.candidatesList.isNotEmpty()) {
-                    _candidateState.value =
-                        CandidateState.Loaded(res.response.candidateList)
-                }
-            }
+            send(req.build())
+        }
+    }
+
+    fun selectCandidate(id: Int) {
+        if (editState == EditState.ES_EMPTY) {
+            // Next word suggestions, offered after a commit
+            send(request {
+                type = CommandType.CMD_SELECT_CANDIDATE
+                candidateId = id
+            })
+            return
+        }
+
+        viewModelScope.launch {
+            handle(EngineManager.sendCommand(request {
+                type = CommandType.CMD_FOCUS_CANDIDATE
+                candidateId = id
+            }).response)
+            handle(EngineManager.sendCommand(request {
+                type = CommandType.CMD_COMMIT
+            }).response)
         }
     }
 
+    private fun send(req: Request) {
+        viewModelScope.launch {
+            handle(EngineManager.sendCommand(req).response)
+        }
+    }
+
+    private fun handle(response: Response) {
+        editState = response.editState
+        if (response.committed) {
+            onCommitText(committedText(response))
+        }
+
+        if (response.candidateList.candidatesList.isNotEmpty()) {
+            _candidateState.value =
+                CandidateState.Loaded(response.candidateList)
+        } else if (response.nextWords.candidatesList.isNotEmpty()) {
+            _candidateState.value =
+                CandidateState.Loaded(response.nextWords)
+        } else if (response.committed) {
+            _candidateState.value = CandidateState.Empty
+        }
+    }
+
+    // Classic mode and next words send the committed text, other modes
+    // commit what is shown in the preedit
+    private fun committedText(response: Response): String =
+        response.committedText.ifEmpty {
+            response.preedit.segmentsList.joinToString("") { it.value }
+        }
+
     fun setKeyBounds(
         keyData: KeyData,
         touchTarget: Rect? = null,
@@ -15,6 +15,14 @@ export const settings = writable({
         tone_mode: '',
         output_mode: '',
         khin_mode: '',
+        annotation_mode: '',
+        script_preference: '',
+        hanji_variant: '',
+        page_size: 9,
+        keyboard_layout: '',
+        ascii_spelling: '',
+        ascii_khin: '',
+        normalization: '',
         input_mode_shortcut: '',
         t2: '',
         t3: '',
@@ -4,3 +4,4 @@ pub use settings::AppSettings;
 pub use settings::CandidateSettings;
 pub use settings::ColorScheme;
 pub use settings::SettingsManager;
+pub use settings::Snippet;
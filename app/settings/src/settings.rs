@@ -6,6 +6,20 @@ use std::path::PathBuf;
 
 use anyhow::anyhow;
 use anyhow::Result;
+use khiin_protos::config::AppAnnotationMode;
+use khiin_protos::config::AppAsciiKhin;
+use khiin_protos::config::AppAsciiSpelling;
+use khiin_protos::config::AppConfig;
+use khiin_protos::config::AppHanjiVariant;
+use khiin_protos::config::AppInputMode;
+use khiin_protos::config::AppKeyboardLayout;
+use khiin_protos::config::AppKhinMode;
+use khiin_protos::config::AppNormalization;
+use khiin_protos::config::AppOutputMode;
+use khiin_protos::config::AppScriptPreference;
+use khiin_protos::config::BoolValue;
+use khiin_protos::config::KeyConfiguration;
+use protobuf::MessageField;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -39,6 +53,13 @@ const TONE_MODE_DEFAULT: &str = "telex";
 const OUTPUT_MODE_DEFAULT: &str = "lomaji";
 const KHIN_MODE_DEFAULT: &str = "hyphen";
 const INPUT_MODE_SHORTCUT_DEFAULT: &str = "default";
+const ANNOTATION_MODE_DEFAULT: &str = "hint";
+const SCRIPT_PREFERENCE_DEFAULT: &str = "any";
+const HANJI_VARIANT_DEFAULT: &str = "standard";
+const KEYBOARD_LAYOUT_DEFAULT: &str = "lomaji";
+const ASCII_SPELLING_DEFAULT: &str = "poj";
+const ASCII_KHIN_DEFAULT: &str = "hyphens";
+const NORMALIZATION_DEFAULT: &str = "font_safe";
 const T2_DEFAULT: char = 's';
 const T3_DEFAULT: char = 'f';
 const T5_DEFAULT: char = 'l';
@@ -49,6 +70,9 @@ const T9_DEFAULT: char = 'w';
 const KHIN_DEFAULT: char = 'v';
 const HYPHEN_DEFAULT: char = 'd';
 const DONE_DEFAULT: char = 'r';
+const PREDICTION_LIMIT_DEFAULT: u32 = 3;
+const NEXT_WORD_LIMIT_DEFAULT: u32 = 5;
+const PAGE_SIZE_DEFAULT: u32 = 9;
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct InputSettings {
@@ -82,6 +106,193 @@ pub struct InputSettings {
     pub hyphen: char,
     #[serde(default = "default_done")]
     pub done: char,
+    /// In telex mode, also accept digits as tone keys
+    #[serde(default)]
+    pub digits_as_fallback: bool,
+    /// Offer corrections for common typing mistakes
+    #[serde(default)]
+    pub fuzzy_matching: bool,
+    /// Spellings typed for each other, such as `ts/ch`. Empty for the
+    /// engine defaults.
+    #[serde(default)]
+    pub fuzzy_confusions: Vec<String>,
+    /// Suggest words that complete the input typed so far
+    #[serde(default)]
+    pub predictions: bool,
+    #[serde(default = "default_prediction_limit")]
+    pub prediction_limit: u32,
+    /// Match the first letter of each syllable, e.g. `tg` for `Tâi-gí`
+    #[serde(default)]
+    pub abbreviations: bool,
+    /// Suggest likely next words after each commit
+    #[serde(default)]
+    pub next_word_predictions: bool,
+    #[serde(default = "default_next_word_limit")]
+    pub next_word_limit: u32,
+    /// Use khin forms from the dictionary without typing the khin key
+    #[serde(default)]
+    pub autokhin: bool,
+    /// Shown next to candidates: `hint`, `reading`, `gloss`, `tps`,
+    /// `ipa` or `none`
+    #[serde(default = "default_annotation_mode")]
+    pub annotation_mode: String,
+    /// In Hanji mode, leave out candidates written only in lomaji
+    #[serde(default)]
+    pub hide_lomaji_candidates: bool,
+    /// In Hanji mode, rank `hanji` or `hanlo` forms first, or `any`
+    #[serde(default = "default_script_preference")]
+    pub script_preference: String,
+    /// Write Hanji in their `standard` or `common` variant forms
+    #[serde(default = "default_hanji_variant")]
+    pub hanji_variant: String,
+    /// Candidates on each page, from 1 to 10
+    #[serde(default = "default_page_size")]
+    pub page_size: u32,
+    /// Type with `lomaji` letters or on a `tps` (zhuyin-style) layout
+    #[serde(default = "default_keyboard_layout")]
+    pub keyboard_layout: String,
+    /// Spell ASCII output in `poj` or `tailo`
+    #[serde(default = "default_ascii_spelling")]
+    pub ascii_spelling: String,
+    /// Mark khin in ASCII output with `hyphens` or tone `zero`
+    #[serde(default = "default_ascii_khin")]
+    pub ascii_khin: String,
+    /// Unicode normalization of output text: `font_safe`, `nfc` or `nfd`
+    #[serde(default = "default_normalization")]
+    pub normalization: String,
+    /// Text offered as the first candidate when its trigger is typed
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Snippet {
+    pub trigger: String,
+    pub value: String,
+}
+
+impl From<&Snippet> for khiin_protos::command::Snippet {
+    fn from(snippet: &Snippet) -> Self {
+        let mut proto = Self::new();
+        proto.trigger = snippet.trigger.clone();
+        proto.value = snippet.value.clone();
+        proto
+    }
+}
+
+/// The engine configuration for these settings. Unrecognized values fall
+/// back to the engine defaults.
+impl From<&InputSettings> for AppConfig {
+    fn from(settings: &InputSettings) -> Self {
+        let mut config = AppConfig::new();
+
+        config.input_mode = match settings.input_mode.as_str() {
+            "manual" => AppInputMode::MANUAL,
+            _ => AppInputMode::CLASSIC,
+        }
+        .into();
+
+        config.output_mode = match settings.output_mode.as_str() {
+            "hanji" => AppOutputMode::HANJI,
+            "tps" => AppOutputMode::TPS,
+            "ascii" => AppOutputMode::ASCII,
+            _ => AppOutputMode::LOMAJI,
+        }
+        .into();
+
+        config.khin_mode = match settings.khin_mode.as_str() {
+            "khinless" => AppKhinMode::KHINLESS,
+            "dot" => AppKhinMode::DOT,
+            _ => AppKhinMode::HYPHEN,
+        }
+        .into();
+
+        config.annotation_mode = match settings.annotation_mode.as_str() {
+            "reading" => AppAnnotationMode::ANNOTATE_READING,
+            "gloss" => AppAnnotationMode::ANNOTATE_GLOSS,
+            "tps" => AppAnnotationMode::ANNOTATE_TPS,
+            "ipa" => AppAnnotationMode::ANNOTATE_IPA,
+            "none" => AppAnnotationMode::ANNOTATE_NONE,
+            _ => AppAnnotationMode::ANNOTATE_HINT,
+        }
+        .into();
+
+        config.script_preference = match settings.script_preference.as_str() {
+            "hanji" => AppScriptPreference::SCRIPT_HANJI,
+            "hanlo" => AppScriptPreference::SCRIPT_HANLO,
+            _ => AppScriptPreference::SCRIPT_ANY,
+        }
+        .into();
+
+        config.hanji_variant = match settings.hanji_variant.as_str() {
+            "common" => AppHanjiVariant::HANJI_COMMON,
+            _ => AppHanjiVariant::HANJI_STANDARD,
+        }
+        .into();
+
+        config.keyboard_layout = match settings.keyboard_layout.as_str() {
+            "tps" => AppKeyboardLayout::KEYBOARD_TPS,
+            _ => AppKeyboardLayout::KEYBOARD_LOMAJI,
+        }
+        .into();
+
+        config.ascii_spelling = match settings.ascii_spelling.as_str() {
+            "tailo" => AppAsciiSpelling::SPELLING_TAILO,
+            _ => AppAsciiSpelling::SPELLING_POJ,
+        }
+        .into();
+
+        config.ascii_khin = match settings.ascii_khin.as_str() {
+            "zero" => AppAsciiKhin::ASCII_KHIN_ZERO,
+            _ => AppAsciiKhin::ASCII_KHIN_HYPHENS,
+        }
+        .into();
+
+        config.normalization = match settings.normalization.as_str() {
+            "nfc" => AppNormalization::NORMALIZE_NFC,
+            "nfd" => AppNormalization::NORMALIZE_NFD,
+            _ => AppNormalization::NORMALIZE_FONT_SAFE,
+        }
+        .into();
+
+        config.input_mode_shortcut = settings.input_mode_shortcut.clone();
+        config.telex_enabled = bool_value(settings.tone_mode == "telex");
+
+        let mut key_config = KeyConfiguration::new();
+        key_config.telex_t2 = settings.t2.to_string();
+        key_config.telex_t3 = settings.t3.to_string();
+        key_config.telex_t5 = settings.t5.to_string();
+        key_config.telex_t6 = settings.t6.to_string();
+        key_config.telex_t7 = settings.t7.to_string();
+        key_config.telex_t8 = settings.t8.to_string();
+        key_config.telex_t9 = settings.t9.to_string();
+        key_config.telex_khin = settings.khin.to_string();
+        key_config.alt_hyphen = settings.hyphen.to_string();
+        key_config.done = settings.done.to_string();
+        key_config.use_digits_as_fallback =
+            bool_value(settings.digits_as_fallback);
+        config.key_config = Some(key_config).into();
+
+        config.fuzzy_enabled = bool_value(settings.fuzzy_matching);
+        config.fuzzy_confusions = settings.fuzzy_confusions.clone();
+        config.prediction_enabled = bool_value(settings.predictions);
+        config.prediction_limit = settings.prediction_limit as i32;
+        config.abbreviation_enabled = bool_value(settings.abbreviations);
+        config.next_word_enabled = bool_value(settings.next_word_predictions);
+        config.next_word_limit = settings.next_word_limit as i32;
+        config.autokhin = bool_value(settings.autokhin);
+        config.hide_lomaji_candidates =
+            bool_value(settings.hide_lomaji_candidates);
+        config.page_size = settings.page_size as i32;
+
+        config
+    }
+}
+
+fn bool_value(value: bool) -> MessageField<BoolValue> {
+    let mut proto = BoolValue::new();
+    proto.value = value;
+    Some(proto).into()
 }
 
 fn default_input_mode() -> String {
@@ -100,6 +311,34 @@ fn default_khin_mode() -> String {
     KHIN_MODE_DEFAULT.to_string()
 }
 
+fn default_annotation_mode() -> String {
+    ANNOTATION_MODE_DEFAULT.to_string()
+}
+
+fn default_script_preference() -> String {
+    SCRIPT_PREFERENCE_DEFAULT.to_string()
+}
+
+fn default_hanji_variant() -> String {
+    HANJI_VARIANT_DEFAULT.to_string()
+}
+
+fn default_keyboard_layout() -> String {
+    KEYBOARD_LAYOUT_DEFAULT.to_string()
+}
+
+fn default_ascii_spelling() -> String {
+    ASCII_SPELLING_DEFAULT.to_string()
+}
+
+fn default_ascii_khin() -> String {
+    ASCII_KHIN_DEFAULT.to_string()
+}
+
+fn default_normalization() -> String {
+    NORMALIZATION_DEFAULT.to_string()
+}
+
 fn default_input_mode_shortcut() -> String {
     INPUT_MODE_SHORTCUT_DEFAULT.to_string()
 }
@@ -144,6 +383,18 @@ fn default_done() -> char {
     DONE_DEFAULT
 }
 
+fn default_prediction_limit() -> u32 {
+    PREDICTION_LIMIT_DEFAULT
+}
+
+fn default_next_word_limit() -> u32 {
+    NEXT_WORD_LIMIT_DEFAULT
+}
+
+fn default_page_size() -> u32 {
+    PAGE_SIZE_DEFAULT
+}
+
 impl Default for InputSettings {
     fn default() -> Self {
         Self {
@@ -162,6 +413,25 @@ impl Default for InputSettings {
             khin: KHIN_DEFAULT,
             hyphen: HYPHEN_DEFAULT,
             done: DONE_DEFAULT,
+            digits_as_fallback: false,
+            fuzzy_matching: false,
+            fuzzy_confusions: Vec::new(),
+            predictions: false,
+            prediction_limit: PREDICTION_LIMIT_DEFAULT,
+            abbreviations: false,
+            next_word_predictions: false,
+            next_word_limit: NEXT_WORD_LIMIT_DEFAULT,
+            autokhin: false,
+            annotation_mode: ANNOTATION_MODE_DEFAULT.to_string(),
+            hide_lomaji_candidates: false,
+            script_preference: SCRIPT_PREFERENCE_DEFAULT.to_string(),
+            hanji_variant: HANJI_VARIANT_DEFAULT.to_string(),
+            page_size: PAGE_SIZE_DEFAULT,
+            keyboard_layout: KEYBOARD_LAYOUT_DEFAULT.to_string(),
+            ascii_spelling: ASCII_SPELLING_DEFAULT.to_string(),
+            ascii_khin: ASCII_KHIN_DEFAULT.to_string(),
+            normalization: NORMALIZATION_DEFAULT.to_string(),
+            snippets: Vec::new(),
         }
     }
 }
@@ -279,5 +549,29 @@ mod tests {
         assert_eq!(settings.input_settings.output_mode, "lomaji");
         assert_eq!(settings.input_settings.t2, 's');
         assert_eq!(settings.input_settings.t3, 'c');
+        assert!(!settings.input_settings.digits_as_fallback);
+        assert!(!settings.input_settings.fuzzy_matching);
+        assert_eq!(settings.input_settings.prediction_limit, 3);
+        assert_eq!(settings.input_settings.next_word_limit, 5);
+    }
+
+    #[test]
+    fn it_converts_to_an_app_config() {
+        let settings = InputSettings {
+            input_mode: "manual".into(),
+            tone_mode: "telex".into(),
+            output_mode: "unknown".into(),
+            t3: 'c',
+            digits_as_fallback: true,
+            ..Default::default()
+        };
+
+        let config = AppConfig::from(&settings);
+        assert_eq!(config.input_mode.enum_value(), Ok(AppInputMode::MANUAL));
+        assert_eq!(config.output_mode.enum_value(), Ok(AppOutputMode::LOMAJI));
+        assert!(config.telex_enabled.value);
+        assert_eq!(config.key_config.telex_t3, "c");
+        assert!(config.key_config.use_digits_as_fallback.value);
+        assert_eq!(config.page_size, 9);
     }
 }
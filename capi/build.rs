@@ -0,0 +1,20 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_file = PathBuf::from(&crate_dir).join("include").join("khiin.h");
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_root_or_default(&crate_dir);
+    match cbindgen::generate_with_config(&crate_dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(out_file);
+        },
+        Err(e) => {
+            // Keep the checked-in header if generation fails
+            println!("cargo:warning=Unable to generate khiin.h: {}", e);
+        },
+    }
+}
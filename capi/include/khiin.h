@@ -0,0 +1,76 @@
+/* libkhiin: C interface to the Khiin engine */
+
+#ifndef KHIIN_H
+#define KHIIN_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of `khiin_engine_send_command`.
+typedef enum KhiinStatus {
+  KHIIN_STATUS_OK = 0,
+  KHIIN_STATUS_NULL_POINTER = 1,
+  KHIIN_STATUS_ENGINE_ERROR = 2,
+  KHIIN_STATUS_PANIC = 3,
+} KhiinStatus;
+
+// Opaque handle to an engine instance.
+typedef struct KhiinEngine KhiinEngine;
+
+// Bytes owned by the library. Must be released with `khiin_free_buffer`.
+typedef struct KhiinBuffer {
+  uint8_t *data;
+  size_t len;
+} KhiinBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Loads the engine from the SQLite database at `db_path` (a NUL-terminated
+// UTF-8 string). Returns NULL if the path is invalid or the database could
+// not be loaded.
+//
+// # Safety
+//
+// `db_path` must be NULL or point to a valid NUL-terminated string.
+struct KhiinEngine *khiin_engine_new(const char *db_path);
+
+// Sends a serialized `Command` (with its `request` set) to the engine and
+// writes the serialized `Command` (with its `response` set) to `out`.
+//
+// On success `out` holds a buffer that the caller must release with
+// `khiin_free_buffer`. On failure `out` is set to an empty buffer.
+//
+// # Safety
+//
+// `engine` must come from `khiin_engine_new` and not yet be freed, `bytes`
+// must point to `len` readable bytes, and `out` must be writable.
+enum KhiinStatus khiin_engine_send_command(struct KhiinEngine *engine,
+                                           const uint8_t *bytes,
+                                           size_t len,
+                                           struct KhiinBuffer *out);
+
+// Releases a buffer returned by `khiin_engine_send_command`.
+//
+// # Safety
+//
+// `buffer` must have been produced by this library and not freed before.
+void khiin_free_buffer(struct KhiinBuffer buffer);
+
+// Releases an engine created by `khiin_engine_new`. Passing NULL is a no-op.
+//
+// # Safety
+//
+// `engine` must be NULL or come from `khiin_engine_new` and not be freed
+// before.
+void khiin_engine_free(struct KhiinEngine *engine);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* KHIIN_H */
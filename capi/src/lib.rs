@@ -0,0 +1,250 @@
+//! C interface to the Khiin engine.
+//!
+//! Hosts create an engine with [`khiin_engine_new`], exchange serialized
+//! `Command` protobufs with [`khiin_engine_send_command`], and release
+//! everything they receive with [`khiin_free_buffer`] and
+//! [`khiin_engine_free`]. The header at `include/khiin.h` is generated from
+//! this file by cbindgen during the build.
+
+use std::ffi::c_char;
+use std::ffi::CStr;
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
+use std::ptr;
+
+use khiin::Engine;
+
+/// Opaque handle to an engine instance.
+pub struct KhiinEngine {
+    engine: Engine,
+}
+
+/// Bytes owned by the library. Must be released with `khiin_free_buffer`.
+#[repr(C)]
+pub struct KhiinBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl KhiinBuffer {
+    fn empty() -> Self {
+        KhiinBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        KhiinBuffer { data, len }
+    }
+}
+
+/// Result of `khiin_engine_send_command`.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum KhiinStatus {
+    Ok = 0,
+    NullPointer = 1,
+    EngineError = 2,
+    Panic = 3,
+}
+
+/// Loads the engine from the SQLite database at `db_path` (a NUL-terminated
+/// UTF-8 string). Returns NULL if the path is invalid or the database could
+/// not be loaded.
+///
+/// # Safety
+///
+/// `db_path` must be NULL or point to a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn khiin_engine_new(
+    db_path: *const c_char,
+) -> *mut KhiinEngine {
+    let _ = env_logger::try_init();
+
+    if db_path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path = match CStr::from_ptr(db_path).to_str() {
+        Ok(path) => path.to_string(),
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let result = catch_unwind(|| Engine::new(&path));
+    match result {
+        Ok(Some(engine)) => {
+            log::debug!("Engine intialized from: {}", path);
+            Box::into_raw(Box::new(KhiinEngine { engine }))
+        },
+        _ => {
+            log::debug!("Unable to initialize engine from: {}", path);
+            ptr::null_mut()
+        },
+    }
+}
+
+/// Sends a serialized `Command` (with its `request` set) to the engine and
+/// writes the serialized `Command` (with its `response` set) to `out`.
+///
+/// On success `out` holds a buffer that the caller must release with
+/// `khiin_free_buffer`. On failure `out` is set to an empty buffer.
+///
+/// # Safety
+///
+/// `engine` must come from `khiin_engine_new` and not yet be freed, `bytes`
+/// must point to `len` readable bytes, and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn khiin_engine_send_command(
+    engine: *mut KhiinEngine,
+    bytes: *const u8,
+    len: usize,
+    out: *mut KhiinBuffer,
+) -> KhiinStatus {
+    if engine.is_null() || out.is_null() || (bytes.is_null() && len > 0) {
+        return KhiinStatus::NullPointer;
+    }
+
+    *out = KhiinBuffer::empty();
+
+    let input = if len == 0 {
+        &[][..]
+    } else {
+        std::slice::from_raw_parts(bytes, len)
+    };
+
+    let engine = &mut (*engine).engine;
+    let result =
+        catch_unwind(AssertUnwindSafe(|| engine.send_command_bytes(input)));
+
+    match result {
+        Ok(Ok(response)) => {
+            *out = KhiinBuffer::from_vec(response);
+            KhiinStatus::Ok
+        },
+        Ok(Err(e)) => {
+            log::debug!("Engine error: {}", e);
+            KhiinStatus::EngineError
+        },
+        Err(_) => KhiinStatus::Panic,
+    }
+}
+
+/// Releases a buffer returned by `khiin_engine_send_command`.
+///
+/// # Safety
+///
+/// `buffer` must have been produced by this library and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn khiin_free_buffer(buffer: KhiinBuffer) {
+    if buffer.data.is_null() {
+        return;
+    }
+
+    let slice = ptr::slice_from_raw_parts_mut(buffer.data, buffer.len);
+    drop(Box::from_raw(slice));
+}
+
+/// Releases an engine created by `khiin_engine_new`. Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `engine` must be NULL or come from `khiin_engine_new` and not be freed
+/// before.
+#[no_mangle]
+pub unsafe extern "C" fn khiin_engine_free(engine: *mut KhiinEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use khiin_protos::command::*;
+    use protobuf::Message;
+
+    use super::*;
+
+    fn db_path() -> CString {
+        let path = std::env::current_dir()
+            .unwrap()
+            .join("../target/debug/khiin.db");
+        CString::new(path.to_str().unwrap()).unwrap()
+    }
+
+    fn send_key_command(ch: char) -> Vec<u8> {
+        let mut req = Request::new();
+        req.type_ = CommandType::CMD_SEND_KEY.into();
+        let mut ke = KeyEvent::new();
+        ke.key_code = ch as i32;
+        req.key_event = Some(ke).into();
+        let mut cmd = Command::new();
+        cmd.request = Some(req).into();
+        cmd.write_to_bytes().unwrap()
+    }
+
+    #[test]
+    fn it_rejects_null_arguments() {
+        unsafe {
+            assert!(khiin_engine_new(ptr::null()).is_null());
+            let mut out = KhiinBuffer::empty();
+            let status = khiin_engine_send_command(
+                ptr::null_mut(),
+                ptr::null(),
+                0,
+                &mut out,
+            );
+            assert_eq!(status, KhiinStatus::NullPointer);
+            khiin_engine_free(ptr::null_mut());
+            khiin_free_buffer(out);
+        }
+    }
+
+    #[test]
+    fn it_sends_commands() {
+        unsafe {
+            let engine = khiin_engine_new(db_path().as_ptr());
+            assert!(!engine.is_null());
+
+            let bytes = send_key_command('a');
+            let mut out = KhiinBuffer::empty();
+            let status = khiin_engine_send_command(
+                engine,
+                bytes.as_ptr(),
+                bytes.len(),
+                &mut out,
+            );
+            assert_eq!(status, KhiinStatus::Ok);
+            assert!(!out.data.is_null());
+
+            let res = std::slice::from_raw_parts(out.data, out.len);
+            let cmd = Command::parse_from_bytes(res).unwrap();
+            assert_eq!(cmd.response.preedit.segments[0].value, "a");
+
+            khiin_free_buffer(out);
+            khiin_engine_free(engine);
+        }
+    }
+
+    #[test]
+    fn it_reports_invalid_commands() {
+        unsafe {
+            let engine = khiin_engine_new(db_path().as_ptr());
+            let bytes = [0xffu8; 4];
+            let mut out = KhiinBuffer::empty();
+            let status = khiin_engine_send_command(
+                engine,
+                bytes.as_ptr(),
+                bytes.len(),
+                &mut out,
+            );
+            assert_eq!(status, KhiinStatus::EngineError);
+            assert!(out.data.is_null());
+            khiin_engine_free(engine);
+        }
+    }
+}
@@ -53,6 +53,8 @@ fn blank_display(
     let output_mode_str = match output_mode {
         AppOutputMode::LOMAJI => "Lomaji",
         AppOutputMode::HANJI => "Hanji",
+        AppOutputMode::TPS => "TPS",
+        AppOutputMode::ASCII => "ASCII",
     };
     update_display(
         stdout,
@@ -215,6 +217,8 @@ fn draw_ime(
     let output_mode_str = match output_mode {
         AppOutputMode::LOMAJI => "Lomaji",
         AppOutputMode::HANJI => "Hanji",
+        AppOutputMode::TPS => "TPS",
+        AppOutputMode::ASCII => "ASCII",
     };
 
     if cmd.response.committed {
@@ -274,6 +278,34 @@ fn read_key() -> Result<KeyEvent> {
     }
 }
 
+/// `khiin_cli keys [--numeric] <word>...`: prints the keys to type each
+/// reading of each word
+pub fn print_key_sequences(stdout: &mut Stdout, args: &[String]) -> Result<()> {
+    let is_telex = !args.iter().any(|arg| arg == "--numeric");
+    let words = args.iter().filter(|arg| !arg.starts_with("--"));
+
+    let mut engine = EngineCtrl::new(get_db_filename()?)?;
+    engine.send_set_config_command(
+        &AppInputMode::CLASSIC,
+        &AppOutputMode::LOMAJI,
+        &AppKhinMode::HYPHEN,
+        is_telex,
+    )?;
+
+    for word in words {
+        let cmd = engine.send_list_key_sequences_command(word)?;
+        let candidates = &cmd.response.candidate_list.candidates;
+        if candidates.is_empty() {
+            writeln!(stdout, "{}: not found", word)?;
+        }
+        for cand in candidates.iter() {
+            writeln!(stdout, "{}\t{}\t{}", word, cand.value, cand.key)?;
+        }
+    }
+
+    Ok(())
+}
+
 pub fn run(stdout: &mut Stdout) -> Result<()> {
     execute!(stdout, EnterAlternateScreen)?;
     enable_raw_mode()?;
@@ -90,6 +90,20 @@ impl EngineCtrl {
         self.send_command(cmd)
     }
 
+    pub fn send_list_key_sequences_command(
+        &mut self,
+        query: &str,
+    ) -> Result<Command> {
+        let mut req = Request::new();
+        req.type_ = CommandType::CMD_LIST_KEY_SEQUENCES.into();
+        req.query = query.to_string();
+
+        let mut cmd = Command::new();
+        cmd.request = Some(req).into();
+
+        self.send_command(cmd)
+    }
+
     pub fn reset(&mut self) -> Result<Command> {
         let mut cmd = Command::new();
         let mut req = Request::new();
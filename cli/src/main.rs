@@ -5,6 +5,11 @@ mod keys;
 use anyhow::Result;
 
 fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
     let mut stdout = std::io::stdout();
-    app::run(&mut stdout)
+
+    match args.first().map(String::as_str) {
+        Some("keys") => app::print_key_sequences(&mut stdout, &args[1..]),
+        _ => app::run(&mut stdout),
+    }
 }
@@ -0,0 +1,187 @@
+//! Lomaji in plain ASCII, with tone numbers, such as `Tai5-gi2`
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::lomaji::strip_tone_diacritic;
+use crate::unicode::*;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Spelling {
+    /// Pe̍h-ōe-jī, such as `chhoa7`
+    #[default]
+    Poj,
+    /// Tâi-lô, such as `tshua7`
+    TaiLo,
+}
+
+/// How a khin (neutral tone) syllable is written
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KhinMark {
+    /// After a double hyphen, keeping its tone number: `tng2--lai5`
+    #[default]
+    Hyphens,
+    /// With tone number 0: `tng2-lai0`
+    Zero,
+}
+
+/// Converts a single lomaji syllable, leaving out any khin mark
+pub fn syllable_to_ascii(syl: &str, spelling: Spelling) -> String {
+    let syl = syl.trim_start_matches(DOT_KHIN);
+    let (body, tone) = strip_tone_diacritic(syl);
+    let mut ret = String::new();
+
+    for ch in body.nfd() {
+        match ch {
+            DOT_ABOVE_RIGHT => ret.push('o'),
+            DOTS_BELOW => ret.push('r'),
+            NASAL_LC => ret.push_str("nn"),
+            NASAL_UC => ret.push_str("NN"),
+            _ => ret.push(ch),
+        }
+    }
+
+    // ṳ is written ir
+    for (from, to) in [("ur", "ir"), ("Ur", "Ir"), ("UR", "IR")] {
+        ret = ret.replace(from, to);
+    }
+
+    if spelling == Spelling::TaiLo {
+        ret = to_tai_lo(&ret);
+    }
+
+    ret.push_str(&(tone as i32).to_string());
+    ret
+}
+
+/// Converts each lomaji syllable in `text`, leaving Hanji, punctuation and
+/// spacing as they are
+pub fn lomaji_to_ascii(
+    text: &str,
+    spelling: Spelling,
+    khin: KhinMark,
+) -> String {
+    let mut ret = String::new();
+    let mut syl = String::new();
+    let mut hyphens = 0;
+
+    for ch in text.chars() {
+        if is_lomaji_char(ch) {
+            syl.push(ch);
+            continue;
+        }
+
+        if !syl.is_empty() {
+            push_syllable(&mut ret, &syl, hyphens, spelling, khin);
+            syl.clear();
+            hyphens = 0;
+        }
+
+        if ch == '-' {
+            hyphens += 1;
+        } else {
+            ret.push_str(&"-".repeat(hyphens));
+            ret.push(ch);
+            hyphens = 0;
+        }
+    }
+
+    if !syl.is_empty() {
+        push_syllable(&mut ret, &syl, hyphens, spelling, khin);
+    } else {
+        ret.push_str(&"-".repeat(hyphens));
+    }
+
+    ret
+}
+
+fn push_syllable(
+    ret: &mut String,
+    syl: &str,
+    hyphens: usize,
+    spelling: Spelling,
+    khin: KhinMark,
+) {
+    let is_khin = hyphens >= 2 || syl.starts_with(DOT_KHIN);
+    let mut ascii = syllable_to_ascii(syl, spelling);
+
+    if !is_khin {
+        ret.push_str(&"-".repeat(hyphens));
+    } else if khin == KhinMark::Hyphens {
+        ret.push_str("--");
+    } else {
+        if hyphens > 0 {
+            ret.push('-');
+        }
+        ascii.pop();
+        ascii.push('0');
+    }
+
+    ret.push_str(&ascii);
+}
+
+fn is_lomaji_char(ch: char) -> bool {
+    (ch.is_alphabetic() && ch < HANJI_CUTOFF)
+        || ('\u{0300}'..='\u{036f}').contains(&ch)
+        || ch == DOT_KHIN
+}
+
+/// Respells an ASCII POJ syllable, keeping the case of its first letter
+fn to_tai_lo(poj: &str) -> String {
+    let lower = poj.to_ascii_lowercase();
+    let mut ret = if let Some(rest) = lower.strip_prefix("chh") {
+        format!("tsh{}", rest)
+    } else if let Some(rest) = lower.strip_prefix("ch") {
+        format!("ts{}", rest)
+    } else {
+        lower
+    };
+
+    ret = ret.replace("oa", "ua").replace("oe", "ue");
+
+    for (from, to) in [("eng", "ing"), ("ek", "ik")] {
+        if let Some(stem) = ret.strip_suffix(from) {
+            ret = format!("{}{}", stem, to);
+        }
+    }
+
+    if poj.len() > 1 && !poj.chars().any(|c| c.is_ascii_lowercase()) {
+        ret.to_ascii_uppercase()
+    } else if poj.starts_with(|c: char| c.is_ascii_uppercase()) {
+        let mut chars = ret.chars();
+        chars
+            .next()
+            .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+            .unwrap_or_default()
+    } else {
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_converts_syllables() {
+        assert_eq!(syllable_to_ascii("Tâi", Spelling::Poj), "Tai5");
+        assert_eq!(syllable_to_ascii("hó͘", Spelling::Poj), "hoo2");
+        assert_eq!(syllable_to_ascii("kiaⁿ", Spelling::Poj), "kiann1");
+        assert_eq!(syllable_to_ascii("chia̍h", Spelling::Poj), "chiah8");
+        assert_eq!(syllable_to_ascii("chhōa", Spelling::TaiLo), "tshua7");
+        assert_eq!(syllable_to_ascii("Chheng", Spelling::TaiLo), "Tshing1");
+        assert_eq!(syllable_to_ascii("sek", Spelling::TaiLo), "sik4");
+    }
+
+    #[test]
+    fn it_converts_text() {
+        let poj = |t| lomaji_to_ascii(t, Spelling::Poj, KhinMark::Hyphens);
+        assert_eq!(poj("Tâi-gí"), "Tai5-gi2");
+        assert_eq!(poj("tńg--lâi"), "tng2--lai5");
+        assert_eq!(poj("tńg-·lâi"), "tng2--lai5");
+        assert_eq!(poj("chia̍h pn̄g, 食飯"), "chiah8 png7, 食飯");
+
+        let zero = |t| lomaji_to_ascii(t, Spelling::Poj, KhinMark::Zero);
+        assert_eq!(zero("tńg--lâi"), "tng2-lai0");
+        assert_eq!(zero("khòaⁿ ·leh"), "khoann3 leh0");
+    }
+}
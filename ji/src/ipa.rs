@@ -0,0 +1,220 @@
+//! IPA transcription of lomaji, with Chao tone letters
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::parts::parse;
+use crate::parts::Parts;
+use crate::parts::O_DOTS_BELOW;
+use crate::parts::U_DOTS_BELOW;
+use crate::Tone;
+
+const NASALIZED: char = '\u{0303}';
+const SYLLABIC: char = '\u{0329}';
+const SYLLABIC_ABOVE: char = '\u{030d}';
+
+const INITIALS: &[(&str, &str)] = &[
+    ("chh", "tsʰ"),
+    ("ch", "ts"),
+    ("ph", "pʰ"),
+    ("th", "tʰ"),
+    ("kh", "kʰ"),
+    ("ng", "ŋ"),
+    ("p", "p"),
+    ("b", "b"),
+    ("m", "m"),
+    ("t", "t"),
+    ("n", "n"),
+    ("l", "l"),
+    ("k", "k"),
+    ("g", "ɡ"),
+    ("h", "h"),
+    ("s", "s"),
+    ("j", "dz"),
+];
+
+// Sibilants are palatalized before i
+const PALATALS: &[(&str, &str)] =
+    &[("ch", "tɕ"), ("chh", "tɕʰ"), ("s", "ɕ"), ("j", "dʑ")];
+
+/// Tone values of the Taipei accent
+fn tone_letters(tone: Tone, khin: bool) -> &'static str {
+    if khin {
+        return "˨";
+    }
+
+    match tone {
+        Tone::T1 => "˦˦",
+        Tone::T2 => "˥˧",
+        Tone::T3 => "˨˩",
+        Tone::T4 => "˧˨",
+        Tone::T5 => "˨˦",
+        Tone::T6 | Tone::T7 => "˧˧",
+        Tone::T8 => "˦",
+        Tone::T9 => "˧˥",
+        Tone::None => "",
+    }
+}
+
+/// Converts a single lomaji syllable in POJ or Tâi-lô, such as `chia̍h`, to
+/// IPA. Returns `None` if it is not a syllable.
+pub fn syllable_to_ipa(syl: &str) -> Option<String> {
+    let parts = parse(syl)?;
+    let mut ret = String::new();
+
+    let palatal = parts.vowels.starts_with('i');
+    let initial = PALATALS
+        .iter()
+        .filter(|_| palatal)
+        .chain(INITIALS)
+        .find(|(i, _)| *i == parts.initial);
+    if let Some((_, ipa)) = initial {
+        ret.push_str(ipa);
+    }
+
+    match parts.syllabic {
+        Some("ng") => {
+            ret.push('ŋ');
+            ret.push(SYLLABIC_ABOVE);
+        },
+        Some(_) => {
+            ret.push('m');
+            ret.push(SYLLABIC);
+        },
+        None => ret.push_str(&vowels(&parts)?),
+    }
+
+    ret.push_str(coda(parts.coda));
+    ret.push_str(tone_letters(parts.tone, parts.khin));
+    Some(ret.nfc().collect())
+}
+
+/// Converts each lomaji syllable in `text`, separated by spaces. Anything
+/// that is not lomaji is left as it is.
+pub fn lomaji_to_ipa(text: &str) -> String {
+    let mut syllables = Vec::new();
+    let mut khin = false;
+
+    for word in text.split_whitespace() {
+        for (i, syl) in word.split('-').enumerate() {
+            if syl.is_empty() {
+                // A double hyphen marks the next syllable as khin
+                khin = i > 0;
+                continue;
+            }
+            let syl = if khin {
+                format!("--{}", syl)
+            } else {
+                syl.to_string()
+            };
+            syllables.push(syllable_to_ipa(&syl).unwrap_or(syl));
+        }
+        khin = false;
+    }
+
+    syllables.join(" ")
+}
+
+fn vowels(parts: &Parts) -> Option<String> {
+    let mut ret = String::new();
+    let mut chars = parts.vowels.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let ipa = match ch {
+            'o' if chars.next_if_eq(&'o').is_some() => 'ɔ',
+            'o' | O_DOTS_BELOW => 'ə',
+            U_DOTS_BELOW => 'ɨ',
+            'a' | 'e' | 'i' | 'u' => ch,
+            _ => return None,
+        };
+        ret.push(ipa);
+        if parts.nasal {
+            ret.push(NASALIZED);
+        }
+    }
+
+    Some(ret)
+}
+
+fn coda(coda: &str) -> &'static str {
+    match coda {
+        "ng" => "ŋ",
+        "m" => "m",
+        "n" => "n",
+        // Unreleased stops
+        "p" => "p\u{031a}",
+        "t" => "t\u{031a}",
+        "k" => "k\u{031a}",
+        "h" => "ʔ",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYLLABLES: &[(&str, &str)] = &[
+        // Initials
+        ("pa", "pa˦˦"),
+        ("phah", "pʰaʔ˧˨"),
+        ("bô", "bə˨˦"),
+        ("mā", "ma˧˧"),
+        ("thâu", "tʰau˨˦"),
+        ("lâng", "laŋ˨˦"),
+        ("góa", "ɡua˥˧"),
+        ("ngó͘", "ŋɔ˥˧"),
+        ("khòaⁿ", "kʰũã˨˩"),
+        ("hó", "hə˥˧"),
+        ("chá", "tsa˥˧"),
+        ("chhài", "tsʰai˨˩"),
+        ("jōa", "dzua˧˧"),
+        ("tsa̍p", "tsap̚˦"),
+        // Palatals before i
+        ("chia̍h", "tɕiaʔ˦"),
+        ("chhiū", "tɕʰiu˧˧"),
+        ("sian", "ɕian˦˦"),
+        ("jit", "dʑit̚˧˨"),
+        // Finals
+        ("tiong", "tiɔŋ˦˦"),
+        ("kok", "kɔk̚˧˨"),
+        ("kheng", "kʰiŋ˦˦"),
+        ("sek", "ɕik̚˧˨"),
+        ("kiaⁿ", "kĩã˦˦"),
+        ("hiⁿh", "hĩʔ˧˨"),
+        ("kam", "kam˦˦"),
+        ("som", "sɔm˦˦"),
+        ("koe", "kue˦˦"),
+        ("o̤", "ə˦˦"),
+        ("ṳ", "ɨ˦˦"),
+        // Syllabic nasals
+        ("m̄", "m̩˧˧"),
+        ("hm̄", "hm̩˧˧"),
+        ("pn̄g", "pŋ̍˧˧"),
+        ("sng", "sŋ̍˦˦"),
+        ("ngh", "ŋ̍ʔ˧˨"),
+        // Khin
+        ("·lâi", "lai˨"),
+    ];
+
+    #[test]
+    fn it_converts_syllables() {
+        for (lomaji, ipa) in SYLLABLES {
+            let res = syllable_to_ipa(lomaji);
+            assert_eq!(res.as_deref(), Some(*ipa), "{}", lomaji);
+        }
+    }
+
+    #[test]
+    fn it_rejects_non_syllables() {
+        for text in ["好", "xyz", "", "123"] {
+            assert!(syllable_to_ipa(text).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn it_converts_text() {
+        assert_eq!(lomaji_to_ipa("chia̍h pn̄g"), "tɕiaʔ˦ pŋ̍˧˧");
+        assert_eq!(lomaji_to_ipa("Tâi-gí"), "tai˨˦ ɡi˥˧");
+        assert_eq!(lomaji_to_ipa("tńg--lâi"), "tŋ̍˥˧ lai˨");
+    }
+}
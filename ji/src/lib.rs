@@ -1,8 +1,14 @@
+pub mod ascii;
+pub mod ipa;
 pub mod lomaji;
 pub mod macros;
+pub mod normalize;
+pub mod numerals;
+mod parts;
 pub mod tone;
 pub mod unicode;
 pub mod punctuation;
+pub mod tps;
 
 pub use lomaji::*;
 pub use tone::*;
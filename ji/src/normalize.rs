@@ -0,0 +1,82 @@
+//! Unicode normalization of output text
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::unicode::*;
+
+/// How combining characters are written in output text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Precomposed letters wherever they exist, with the tone mark written
+    /// before the dots below in `o̤̍`, as in the dictionary
+    #[default]
+    FontSafe,
+    /// Canonical composition
+    Nfc,
+    /// Canonical decomposition, with every tone mark as a combining character
+    Nfd,
+}
+
+/// Normalizes `text` to `form`. Compatibility characters such as `ⁿ` are left
+/// as they are.
+pub fn normalize(text: &str, form: Normalization) -> String {
+    match form {
+        Normalization::FontSafe => {
+            let nfc: String = text.nfc().collect();
+            // NFC orders the dots below (ccc 220) before the vertical line
+            // above (ccc 230), which many fonts stack badly
+            let canonical = format!("{}{}", DOTS_BELOW, TONE_8);
+            nfc.replace(&canonical, &format!("{}{}", TONE_8, DOTS_BELOW))
+        },
+        Normalization::Nfc => text.nfc().collect(),
+        Normalization::Nfd => text.nfd().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forms(text: &str) -> [String; 3] {
+        [
+            normalize(text, Normalization::FontSafe),
+            normalize(text, Normalization::Nfc),
+            normalize(text, Normalization::Nfd),
+        ]
+    }
+
+    #[test]
+    fn it_normalizes_dot_above_right() {
+        // hó͘, typed with the tone mark last
+        let [safe, nfc, nfd] = forms("ho\u{0358}\u{0301}");
+        assert_eq!(safe, "h\u{00f3}\u{0358}");
+        assert_eq!(nfc, "h\u{00f3}\u{0358}");
+        assert_eq!(nfd, "ho\u{0301}\u{0358}");
+    }
+
+    #[test]
+    fn it_keeps_nasal_superscript() {
+        let [safe, nfc, nfd] = forms("ki\u{00e2}\u{207f}");
+        assert_eq!(safe, "ki\u{00e2}\u{207f}");
+        assert_eq!(nfc, "ki\u{00e2}\u{207f}");
+        assert_eq!(nfd, "kia\u{0302}\u{207f}");
+    }
+
+    #[test]
+    fn it_normalizes_dots_below() {
+        // ṳ́ has a precomposed ṳ
+        let [safe, nfc, nfd] = forms("u\u{0301}\u{0324}");
+        assert_eq!(safe, "\u{1e73}\u{0301}");
+        assert_eq!(nfc, "\u{1e73}\u{0301}");
+        assert_eq!(nfd, "u\u{0324}\u{0301}");
+
+        // o̤̍ has no precomposed form
+        let [safe, nfc, nfd] = forms("o\u{030d}\u{0324}");
+        assert_eq!(safe, "o\u{030d}\u{0324}");
+        assert_eq!(nfc, "o\u{0324}\u{030d}");
+        assert_eq!(nfd, "o\u{0324}\u{030d}");
+
+        let [safe, ..] = forms("\u{00f3}\u{0324}");
+        assert_eq!(safe, "\u{00f3}\u{0324}");
+    }
+}
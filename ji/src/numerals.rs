@@ -0,0 +1,389 @@
+//! Taiwanese readings of numbers, dates, times and money amounts
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// One way to read a number, in Hanji and in lomaji
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reading {
+    pub hanji: String,
+    pub lomaji: String,
+}
+
+/// A Hanji character and its lomaji syllable
+type Syl = (&'static str, &'static str);
+
+/// Syllables that are hyphenated together in lomaji
+type Word = Vec<Syl>;
+
+const COLLOQUIAL: [Syl; 10] = [
+    ("空", "khòng"),
+    ("一", "chi̍t"),
+    ("兩", "nn̄g"),
+    ("三", "saⁿ"),
+    ("四", "sì"),
+    ("五", "gō͘"),
+    ("六", "la̍k"),
+    ("七", "chhit"),
+    ("八", "peh"),
+    ("九", "káu"),
+];
+
+const LITERARY: [Syl; 10] = [
+    ("零", "lêng"),
+    ("一", "it"),
+    ("二", "jī"),
+    ("三", "sam"),
+    ("四", "sù"),
+    ("五", "ngó͘"),
+    ("六", "lio̍k"),
+    ("七", "chhit"),
+    ("八", "pat"),
+    ("九", "kiú"),
+];
+
+// Units within each group of four digits, from the ones place up
+const COLLOQUIAL_UNITS: [Syl; 4] =
+    [("", ""), ("十", "cha̍p"), ("百", "pah"), ("千", "chheng")];
+const LITERARY_UNITS: [Syl; 4] =
+    [("", ""), ("十", "si̍p"), ("百", "pek"), ("千", "chhian")];
+
+// Units of each group of four digits
+const GROUPS: [Syl; 3] = [("", ""), ("萬", "bān"), ("億", "ek")];
+
+/// Longest number read with units, up to 9999 億
+const MAX_COUNTED_LEN: usize = 12;
+
+const POINT: Syl = ("點", "tiám");
+const HALF: Syl = ("半", "pòaⁿ");
+const MINUTE: Syl = ("分", "hun");
+const YEAR: Syl = ("年", "nî");
+const MONTH: Syl = ("月", "go̍eh");
+const DAY: Syl = ("日", "ji̍t");
+const DAY_NUMBER: Syl = ("號", "hō");
+const DOLLAR: Syl = ("箍", "kho͘");
+const DIME: Syl = ("角", "kak");
+
+static NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[0-9]+$").unwrap());
+static DECIMAL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([0-9]+)\.([0-9]+)$").unwrap());
+static DATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:([0-9]{4})/)?([0-9]{1,2})/([0-9]{1,2})$").unwrap()
+});
+static TIME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([0-9]{1,2}):([0-9]{2})$").unwrap());
+static MONEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\$([0-9]+)(?:\.([0-9]))?$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    /// Everyday speech, e.g. 兩千空二十四 nn̄g-chheng khòng jī-cha̍p-sì
+    Colloquial,
+    /// Reading pronunciations, e.g. 二千零二十四 jī-chhian lêng jī-si̍p-sù
+    Literary,
+}
+
+/// How a colloquial digit is read depends on where it stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// Counting, or before 百, 千, 萬 or 億: 一 chi̍t, 兩 nn̄g
+    Count,
+    /// Before 十: 一 chi̍t, 二 jī
+    Tens,
+    /// In the ones place after a larger unit, or in a month, day or digit
+    /// string: 一 it, 二 jī
+    Ones,
+}
+
+fn digit(d: usize, role: Role, register: Register) -> Syl {
+    match (register, d, role) {
+        (Register::Literary, ..) => LITERARY[d],
+        (_, 1, Role::Ones) => ("一", "it"),
+        (_, 2, Role::Tens | Role::Ones) => ("二", "jī"),
+        _ => COLLOQUIAL[d],
+    }
+}
+
+/// Reads `n` with units. An `ordinal` number reads a lone 1 or 2 as it or
+/// jī, as in months and days.
+fn count(n: u64, register: Register, ordinal: bool) -> Vec<Word> {
+    let (zero, units) = match register {
+        Register::Colloquial => (COLLOQUIAL[0], COLLOQUIAL_UNITS),
+        Register::Literary => (LITERARY[0], LITERARY_UNITS),
+    };
+
+    if n == 0 {
+        return vec![vec![zero]];
+    }
+
+    let digits: Vec<usize> =
+        n.to_string().bytes().map(|b| (b - b'0') as usize).collect();
+    let mut words: Vec<Word> = Vec::new();
+    let mut zeros = false;
+
+    for (i, &d) in digits.iter().enumerate() {
+        let place = digits.len() - 1 - i;
+        let (unit, group) = (place % 4, place / 4);
+        let after_tens = unit == 0 && i > 0 && digits[i - 1] != 0;
+
+        if d == 0 {
+            zeros |= !words.is_empty();
+        } else {
+            if zeros {
+                // A run of zeros is read once
+                words.push(vec![zero]);
+                zeros = false;
+            }
+
+            let leading = words.is_empty();
+            let role = match unit {
+                1 => Role::Tens,
+                0 if after_tens => Role::Ones,
+                0 if group > 0 => Role::Count,
+                0 if !leading || ordinal => Role::Ones,
+                _ => Role::Count,
+            };
+
+            let mut word = Vec::new();
+            // 10 to 19 start with 十 alone
+            if !(leading && unit == 1 && d == 1) {
+                word.push(digit(d, role, register));
+            }
+            if unit > 0 {
+                word.push(units[unit]);
+            }
+
+            match words.last_mut() {
+                Some(last) if after_tens && !zeros => last.extend(word),
+                _ => words.push(word),
+            }
+        }
+
+        let group_value = n / 10u64.pow(4 * group as u32) % 10000;
+        if unit == 0 && group > 0 && group_value > 0 {
+            if let Some(last) = words.last_mut() {
+                last.push(GROUPS[group]);
+            }
+        }
+    }
+
+    words
+}
+
+/// Reads each digit in turn, as in years and phone numbers
+fn digit_string(digits: &str, register: Register) -> Word {
+    digits
+        .bytes()
+        .map(|b| digit((b - b'0') as usize, Role::Ones, register))
+        .collect()
+}
+
+fn render(words: &[Word]) -> Reading {
+    let hanji = words.iter().flatten().map(|(hanji, _)| *hanji).collect();
+    let lomaji = words
+        .iter()
+        .map(|word| {
+            word.iter()
+                .map(|(_, lomaji)| *lomaji)
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Reading { hanji, lomaji }
+}
+
+/// Appends `syl` to the last word of `words`
+fn suffixed(mut words: Vec<Word>, syl: Syl) -> Vec<Word> {
+    if let Some(last) = words.last_mut() {
+        last.push(syl);
+    }
+    words
+}
+
+fn parse(digits: &str) -> Option<u64> {
+    if digits.len() > MAX_COUNTED_LEN {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Readings of a number, date, time or money amount typed in ASCII, such as
+/// `2024`, `3.5`, `3/15`, `2024/3/15`, `9:30` or `$100`. Empty for any other
+/// text.
+pub fn readings(text: &str) -> Vec<Reading> {
+    let mut ret = Vec::new();
+
+    if NUMBER.is_match(text) {
+        let counted = text == "0" || !text.starts_with('0');
+        if let Some(n) = parse(text).filter(|_| counted) {
+            ret.push(render(&count(n, Register::Colloquial, false)));
+        }
+        if text.len() > 1 {
+            ret.push(render(&[digit_string(text, Register::Colloquial)]));
+        }
+        if let Some(n) = parse(text).filter(|_| counted) {
+            ret.push(render(&count(n, Register::Literary, false)));
+        }
+    } else if let Some(caps) = DECIMAL.captures(text) {
+        if let Some(n) = parse(&caps[1]) {
+            for register in [Register::Colloquial, Register::Literary] {
+                let mut words = suffixed(count(n, register, false), POINT);
+                words.push(digit_string(&caps[2], register));
+                ret.push(render(&words));
+            }
+        }
+    } else if let Some(caps) = DATE.captures(text) {
+        let month = caps[2].parse().unwrap_or(0);
+        let day = caps[3].parse().unwrap_or(0);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return ret;
+        }
+
+        let mut words = Vec::new();
+        if let Some(year) = caps.get(1) {
+            let mut word = digit_string(year.as_str(), Register::Colloquial);
+            word.push(YEAR);
+            words.push(word);
+        }
+        words.extend(suffixed(count(month, Register::Colloquial, true), MONTH));
+
+        for unit in [DAY_NUMBER, DAY] {
+            let day = suffixed(count(day, Register::Colloquial, true), unit);
+            ret.push(render(&[words.clone(), day].concat()));
+        }
+    } else if let Some(caps) = TIME.captures(text) {
+        let hour = caps[1].parse().unwrap_or(99);
+        let minute = caps[2].parse().unwrap_or(99);
+        if hour > 24 || minute > 59 {
+            return ret;
+        }
+
+        let hour = suffixed(count(hour, Register::Colloquial, false), POINT);
+        if minute == 30 {
+            ret.push(render(&suffixed(hour.clone(), HALF)));
+        }
+        let mut words = hour;
+        if minute > 0 {
+            words.extend(suffixed(
+                count(minute, Register::Colloquial, false),
+                MINUTE,
+            ));
+        }
+        ret.push(render(&words));
+    } else if let Some(caps) = MONEY.captures(text) {
+        let Some(dollars) = parse(&caps[1]) else {
+            return ret;
+        };
+        let dimes = caps.get(2).and_then(|d| d.as_str().parse().ok());
+
+        let mut words = Vec::new();
+        if dollars > 0 || dimes.unwrap_or(0) == 0 {
+            words = count(dollars, Register::Colloquial, false);
+            words.push(vec![DOLLAR]);
+        }
+        if let Some(dimes) = dimes.filter(|&d| d > 0) {
+            words.extend(count(dimes, Register::Colloquial, false));
+            words.push(vec![DIME]);
+        }
+        ret.push(render(&words));
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COUNTED: &[(u64, &str, &str)] = &[
+        (0, "空", "khòng"),
+        (1, "一", "chi̍t"),
+        (2, "兩", "nn̄g"),
+        (10, "十", "cha̍p"),
+        (12, "十二", "cha̍p-jī"),
+        (20, "二十", "jī-cha̍p"),
+        (21, "二十一", "jī-cha̍p-it"),
+        (22, "二十二", "jī-cha̍p-jī"),
+        (100, "一百", "chi̍t-pah"),
+        (102, "一百空二", "chi̍t-pah khòng jī"),
+        (110, "一百一十", "chi̍t-pah chi̍t-cha̍p"),
+        (250, "兩百五十", "nn̄g-pah gō͘-cha̍p"),
+        (2024, "兩千空二十四", "nn̄g-chheng khòng jī-cha̍p-sì"),
+        (20000, "兩萬", "nn̄g-bān"),
+        (120000, "十二萬", "cha̍p-jī-bān"),
+        (200005, "二十萬空五", "jī-cha̍p-bān khòng gō͘"),
+        (100020000, "一億空兩萬", "chi̍t-ek khòng nn̄g-bān"),
+    ];
+
+    #[test]
+    fn it_counts() {
+        for (n, hanji, lomaji) in COUNTED {
+            let reading = render(&count(*n, Register::Colloquial, false));
+            assert_eq!(reading.hanji, *hanji, "{}", n);
+            assert_eq!(reading.lomaji, *lomaji, "{}", n);
+        }
+    }
+
+    #[test]
+    fn it_reads_numbers() {
+        let res = readings("2024");
+        assert_eq!(res[0].hanji, "兩千空二十四");
+        assert_eq!(res[1].hanji, "二空二四");
+        assert_eq!(res[1].lomaji, "jī-khòng-jī-sì");
+        assert_eq!(res[2].hanji, "二千零二十四");
+        assert_eq!(res[2].lomaji, "jī-chhian lêng jī-si̍p-sù");
+
+        let res = readings("007");
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].hanji, "空空七");
+
+        let res = readings("3.14");
+        assert_eq!(res[0].lomaji, "saⁿ-tiám it-sì");
+        assert_eq!(res[1].hanji, "三點一四");
+    }
+
+    #[test]
+    fn it_reads_dates() {
+        let res = readings("3/15");
+        assert_eq!(res[0].hanji, "三月十五號");
+        assert_eq!(res[0].lomaji, "saⁿ-go̍eh cha̍p-gō͘-hō");
+        assert_eq!(res[1].hanji, "三月十五日");
+
+        let res = readings("2024/1/2");
+        assert_eq!(res[0].lomaji, "jī-khòng-jī-sì-nî it-go̍eh jī-hō");
+        assert!(readings("13/1").is_empty());
+    }
+
+    #[test]
+    fn it_reads_times() {
+        let res = readings("2:30");
+        assert_eq!(res[0].lomaji, "nn̄g-tiám-pòaⁿ");
+        assert_eq!(res[1].hanji, "兩點三十分");
+        assert_eq!(readings("10:00")[0].hanji, "十點");
+        assert!(readings("9:60").is_empty());
+    }
+
+    #[test]
+    fn it_reads_money() {
+        assert_eq!(readings("$2")[0].lomaji, "nn̄g kho͘");
+        assert_eq!(readings("$1500")[0].hanji, "一千五百箍");
+        assert_eq!(readings("$3.5")[0].lomaji, "saⁿ kho͘ gō͘ kak");
+        assert_eq!(readings("$0.5")[0].hanji, "五角");
+    }
+
+    #[test]
+    fn it_ignores_other_text() {
+        for text in ["", "abc", "1/2/3", "$", "1:2", "12a"] {
+            assert!(readings(text).is_empty(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn it_ignores_non_ascii_digits() {
+        for text in ["٣", "１２", "$٣", "١/٢", "１:３０", "٣.١"] {
+            assert!(readings(text).is_empty(), "{}", text);
+        }
+    }
+}
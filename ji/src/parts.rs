@@ -0,0 +1,180 @@
+//! Lomaji syllables split into initial, vowels, coda and tone
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::lomaji::strip_khin;
+use crate::lomaji::strip_tone_diacritic;
+use crate::unicode::*;
+use crate::Tone;
+
+/// Placeholders for the dotted vowels o̤ and ṳ
+pub(crate) const O_DOTS_BELOW: char = 'ə';
+pub(crate) const U_DOTS_BELOW: char = 'ɨ';
+
+// Longest first, so that `chh` is not read as `ch`
+const INITIALS: &[&str] = &[
+    "chh", "ch", "ph", "th", "kh", "ng", "p", "b", "m", "t", "n", "l", "k",
+    "g", "h", "s", "j",
+];
+
+const CODAS: &[&str] = &["ng", "m", "n", "p", "t", "k", "h"];
+
+/// A syllable in POJ or Tâi-lô, with one spelling for each sound
+#[derive(Debug, PartialEq)]
+pub(crate) struct Parts {
+    pub khin: bool,
+    /// In POJ spelling, or empty
+    pub initial: &'static str,
+    /// The `m` or `ng` of a syllable without vowels, such as m̄ or pn̄g
+    pub syllabic: Option<&'static str>,
+    /// As pronounced: `oo` for o͘, also written o before ng, k, m and p;
+    /// `u` for o before a and e; `i` for e before ng and k
+    pub vowels: String,
+    pub nasal: bool,
+    /// One of `ng`, `m`, `n`, `p`, `t`, `k` or `h`, or empty
+    pub coda: &'static str,
+    pub tone: Tone,
+}
+
+/// Splits a syllable such as `chia̍h` or `·lâi`, or returns `None` if it is
+/// not one
+pub(crate) fn parse(syl: &str) -> Option<Parts> {
+    let mut syl = syl.to_string();
+    let khin = strip_khin(&mut syl);
+    let (body, tone) = strip_tone_diacritic(&syl);
+    let mut body = normalize(&body)?;
+
+    let nasal = body.ends_with("nn");
+    if nasal {
+        body.truncate(body.len() - 2);
+    }
+
+    let mut initial = INITIALS
+        .iter()
+        .find(|i| body.starts_with(*i))
+        .copied()
+        .unwrap_or("");
+    let mut rest = &body[initial.len()..];
+    let at = rest.find(|ch| !is_vowel(ch)).unwrap_or(rest.len());
+    let (vowels, coda) = rest.split_at(at);
+
+    let mut parts = Parts {
+        khin,
+        initial,
+        syllabic: None,
+        vowels: String::new(),
+        nasal,
+        coda: "",
+        tone,
+    };
+
+    if vowels.is_empty() {
+        if nasal {
+            return None;
+        }
+        if matches!((initial, rest), ("m" | "ng", "" | "h")) {
+            initial = "";
+            rest = &body;
+        }
+        let syllabic = ["ng", "m"].into_iter().find(|s| rest.starts_with(s))?;
+        parts.initial = initial;
+        parts.syllabic = Some(syllabic);
+        parts.coda = match &rest[syllabic.len()..] {
+            "" => "",
+            "h" => "h",
+            _ => return None,
+        };
+        return Some(parts);
+    }
+
+    parts.coda = if coda.is_empty() {
+        ""
+    } else {
+        *CODAS.iter().find(|c| **c == coda)?
+    };
+    parts.vowels = vowels.replace("oa", "ua").replace("oe", "ue");
+
+    if ["ng", "k", "m", "p"].contains(&parts.coda)
+        && parts.vowels.ends_with('o')
+        && !parts.vowels.ends_with("oo")
+    {
+        parts.vowels.push('o');
+    }
+
+    if ["ng", "k"].contains(&parts.coda) && parts.vowels.ends_with('e') {
+        parts.vowels.pop();
+        parts.vowels.push('i');
+    }
+
+    Some(parts)
+}
+
+fn is_vowel(ch: char) -> bool {
+    "aeiou".contains(ch) || ch == O_DOTS_BELOW || ch == U_DOTS_BELOW
+}
+
+/// Lowercase ASCII, with `o͘` as `oo`, `ⁿ` as `nn`, Tâi-lô `ts` as `ch`
+/// and dotted vowels as placeholders
+fn normalize(body: &str) -> Option<String> {
+    let mut ret = String::new();
+    for ch in body.nfd().flat_map(char::to_lowercase) {
+        match ch {
+            DOT_ABOVE_RIGHT if ret.ends_with('o') => ret.push('o'),
+            DOTS_BELOW if ret.ends_with('o') => {
+                ret.pop();
+                ret.push(O_DOTS_BELOW);
+            },
+            DOTS_BELOW if ret.ends_with('u') => {
+                ret.pop();
+                ret.push(U_DOTS_BELOW);
+            },
+            NASAL_LC | NASAL_UC => ret.push_str("nn"),
+            DOT_KHIN => {},
+            'a'..='z' => ret.push(ch),
+            _ => return None,
+        }
+    }
+
+    if ret.is_empty() {
+        return None;
+    }
+
+    if let Some(rest) = ret.strip_prefix("ts") {
+        ret = format!("ch{}", rest);
+    }
+
+    // Nasal written before a final h, as in hiⁿh
+    if let Some(stripped) = ret.strip_suffix("nnh") {
+        ret = format!("{}hnn", stripped);
+    }
+
+    Some(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_splits_syllables() {
+        let parts = parse("chhōa").unwrap();
+        assert_eq!(parts.initial, "chh");
+        assert_eq!(parts.vowels, "ua");
+        assert_eq!(parts.tone, Tone::T7);
+
+        let parts = parse("tsing").unwrap();
+        assert_eq!((parts.initial, parts.vowels.as_str()), ("ch", "i"));
+        assert_eq!(parse("cheng").unwrap().vowels, "i");
+        assert_eq!(parse("tiong").unwrap().vowels, "ioo");
+        assert_eq!(parse("·hiⁿh").unwrap().coda, "h");
+        assert!(parse("·hiⁿh").unwrap().nasal);
+
+        let parts = parse("pn̄g").unwrap();
+        assert_eq!((parts.initial, parts.syllabic), ("p", Some("ng")));
+        let parts = parse("m̄").unwrap();
+        assert_eq!((parts.initial, parts.syllabic), ("", Some("m")));
+
+        assert!(parse("xyz").is_none());
+        assert!(parse("好").is_none());
+    }
+}
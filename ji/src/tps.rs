@@ -0,0 +1,249 @@
+//! Taiwanese Phonetic Symbols (臺灣方音符號), converted from lomaji
+
+use crate::parts::parse;
+use crate::parts::Parts;
+use crate::parts::O_DOTS_BELOW;
+use crate::parts::U_DOTS_BELOW;
+use crate::unicode::*;
+use crate::Tone;
+
+/// Marks a khin (neutral tone) syllable
+pub const TPS_KHIN: char = '˙';
+
+const INITIALS: &[(&str, &str)] = &[
+    ("chh", "ㄘ"),
+    ("ch", "ㄗ"),
+    ("ph", "ㄆ"),
+    ("th", "ㄊ"),
+    ("kh", "ㄎ"),
+    ("ng", "ㄫ"),
+    ("p", "ㄅ"),
+    ("b", "ㆠ"),
+    ("m", "ㄇ"),
+    ("t", "ㄉ"),
+    ("n", "ㄋ"),
+    ("l", "ㄌ"),
+    ("k", "ㄍ"),
+    ("g", "ㆣ"),
+    ("h", "ㄏ"),
+    ("s", "ㄙ"),
+    ("j", "ㆡ"),
+];
+
+// Sibilants are written differently before i
+const PALATALS: &[(&str, &str)] =
+    &[("ㄗ", "ㄐ"), ("ㄘ", "ㄑ"), ("ㄙ", "ㄒ"), ("ㆡ", "ㆢ")];
+
+const NASALS: &[(char, char)] = &[
+    ('ㄚ', 'ㆩ'),
+    ('ㄧ', 'ㆪ'),
+    ('ㄨ', 'ㆫ'),
+    ('ㆤ', 'ㆥ'),
+    ('ㆦ', 'ㆧ'),
+    ('ㄜ', 'ㆧ'),
+    ('ㄞ', 'ㆮ'),
+    ('ㄠ', 'ㆯ'),
+];
+
+fn tone_mark(tone: Tone) -> &'static str {
+    match tone {
+        Tone::T2 => "ˋ",
+        Tone::T3 => "˪",
+        Tone::T5 => "ˊ",
+        Tone::T7 => "˫",
+        Tone::T8 => "˙",
+        // Not in the MOE table, but used for the same tones elsewhere
+        Tone::T6 => "ˇ",
+        Tone::T9 => "ˆ",
+        _ => "",
+    }
+}
+
+/// Converts a single lomaji syllable in POJ or Tâi-lô, such as `chia̍h` or
+/// `·lâi`. Returns `None` if it is not a syllable.
+pub fn syllable_to_tps(syl: &str) -> Option<String> {
+    let parts = parse(syl)?;
+    let mut ret = String::new();
+    if parts.khin {
+        ret.push(TPS_KHIN);
+    }
+
+    let initial = INITIALS.iter().find(|(i, _)| *i == parts.initial);
+    if let Some((_, sym)) = initial {
+        if parts.vowels.starts_with('i') {
+            let palatal = PALATALS.iter().find(|(s, _)| s == sym);
+            ret.push_str(palatal.map_or(sym, |(_, p)| p));
+        } else {
+            ret.push_str(sym);
+        }
+    }
+
+    match parts.syllabic {
+        Some(nucleus) => {
+            ret.push(if nucleus == "ng" { 'ㆭ' } else { 'ㆬ' });
+            ret.push_str(coda_symbol(parts.coda));
+        },
+        None => ret.push_str(&rhyme(&parts)?),
+    }
+
+    ret.push_str(tone_mark(parts.tone));
+    Some(ret)
+}
+
+/// Converts each lomaji syllable in `text`, leaving Hanji and anything else
+/// that is not lomaji as it is. Syllables are separated by spaces.
+pub fn lomaji_to_tps(text: &str) -> String {
+    let mut ret = String::new();
+    let mut syl = String::new();
+    let mut hyphens = 0;
+
+    for ch in text.chars() {
+        if ch == '-' {
+            flush_syllable(&mut syl, hyphens, &mut ret);
+            hyphens += 1;
+        } else if ch == ' ' || is_hanji(ch) || ch.is_ascii_punctuation() {
+            flush_syllable(&mut syl, hyphens, &mut ret);
+            hyphens = 0;
+            if ch != ' ' || !ret.chars().last().is_some_and(is_tps) {
+                ret.push(ch);
+            }
+        } else {
+            if syl.is_empty() && hyphens == 1 {
+                hyphens = 0;
+            }
+            syl.push(ch);
+        }
+    }
+    flush_syllable(&mut syl, hyphens, &mut ret);
+
+    ret
+}
+
+fn flush_syllable(syl: &mut String, hyphens: usize, ret: &mut String) {
+    if syl.is_empty() {
+        return;
+    }
+    if hyphens >= 2 {
+        syl.insert_str(0, "--");
+    }
+    match syllable_to_tps(syl) {
+        Some(tps) => {
+            if ret.chars().last().is_some_and(is_tps) {
+                ret.push(' ');
+            }
+            ret.push_str(&tps);
+        },
+        None => ret.push_str(syl),
+    }
+    syl.clear();
+}
+
+/// Whether `ch` is a phonetic symbol or tone mark from this module
+pub fn is_tps(ch: char) -> bool {
+    matches!(ch, '\u{3105}'..='\u{312f}' | '\u{31a0}'..='\u{31bf}')
+        || "˙ˋ˪ˊ˫ˇˆ".contains(ch)
+}
+
+fn coda_symbol(coda: &str) -> &'static str {
+    match coda {
+        "ng" => "ㄥ",
+        "m" => "ㆬ",
+        "n" => "ㄣ",
+        "p" => "ㆴ",
+        "t" => "ㆵ",
+        "k" => "ㆶ",
+        "h" => "ㆷ",
+        _ => "",
+    }
+}
+
+fn rhyme(parts: &Parts) -> Option<String> {
+    let mut syms: Vec<char> = Vec::new();
+    let chars: Vec<char> = parts.vowels.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let final_pair = i + 2 == chars.len();
+        let (sym, len) = match (chars[i], chars.get(i + 1)) {
+            ('o', Some('o')) => ('ㆦ', 2),
+            ('a', Some('i')) if final_pair => ('ㄞ', 2),
+            ('a', Some('u')) if final_pair => ('ㄠ', 2),
+            ('a', _) => ('ㄚ', 1),
+            ('i', _) => ('ㄧ', 1),
+            ('u', _) => ('ㄨ', 1),
+            ('e', _) => ('ㆤ', 1),
+            ('o' | O_DOTS_BELOW, _) => ('ㄜ', 1),
+            (U_DOTS_BELOW, _) => ('ㆨ', 1),
+            _ => return None,
+        };
+        syms.push(sym);
+        i += len;
+    }
+
+    if parts.nasal {
+        let last = syms.last_mut()?;
+        *last = NASALS.iter().find(|(v, _)| v == last)?.1;
+    }
+
+    // Vowels fused with a nasal coda
+    let fused = match (syms.last(), parts.coda) {
+        (Some('ㄚ'), "n") => Some('ㄢ'),
+        (Some('ㄚ'), "ng") => Some('ㄤ'),
+        (Some('ㄚ'), "m") => Some('ㆰ'),
+        (Some('ㆦ'), "ng") => Some('ㆲ'),
+        (Some('ㆦ'), "m") => Some('ㆱ'),
+        _ => None,
+    };
+
+    let mut ret = String::new();
+    match fused {
+        Some(sym) => {
+            syms.pop();
+            syms.push(sym);
+            ret.extend(syms);
+        },
+        None => {
+            ret.extend(syms);
+            ret.push_str(coda_symbol(parts.coda));
+        },
+    }
+
+    Some(ret)
+}
+
+fn is_hanji(ch: char) -> bool {
+    ch > HANJI_CUTOFF && !is_tps(ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_converts_syllables() {
+        assert_eq!(syllable_to_tps("chia̍h").unwrap(), "ㄐㄧㄚㆷ˙");
+        assert_eq!(syllable_to_tps("pn̄g").unwrap(), "ㄅㆭ˫");
+        assert_eq!(syllable_to_tps("góa").unwrap(), "ㆣㄨㄚˋ");
+        assert_eq!(syllable_to_tps("lâng").unwrap(), "ㄌㄤˊ");
+        assert_eq!(syllable_to_tps("hó͘").unwrap(), "ㄏㆦˋ");
+        assert_eq!(syllable_to_tps("sian").unwrap(), "ㄒㄧㄢ");
+        assert_eq!(syllable_to_tps("tiong").unwrap(), "ㄉㄧㆲ");
+        assert_eq!(syllable_to_tps("kheng").unwrap(), "ㄎㄧㄥ");
+        assert_eq!(syllable_to_tps("kiaⁿ").unwrap(), "ㄍㄧㆩ");
+        assert_eq!(syllable_to_tps("phaiⁿ").unwrap(), "ㄆㆮ");
+        assert_eq!(syllable_to_tps("m̄").unwrap(), "ㆬ˫");
+        assert_eq!(syllable_to_tps("·lâi").unwrap(), "˙ㄌㄞˊ");
+        assert_eq!(syllable_to_tps("tsa̍p").unwrap(), "ㄗㄚㆴ˙");
+        assert!(syllable_to_tps("好").is_none());
+        assert!(syllable_to_tps("xyz").is_none());
+    }
+
+    #[test]
+    fn it_converts_text() {
+        assert_eq!(lomaji_to_tps("chia̍h pn̄g"), "ㄐㄧㄚㆷ˙ ㄅㆭ˫");
+        assert_eq!(lomaji_to_tps("tńg--lâi"), "ㄉㆭˋ ˙ㄌㄞˊ");
+        assert_eq!(lomaji_to_tps("sió-tī"), "ㄒㄧㄜˋ ㄉㄧ˫");
+        assert_eq!(lomaji_to_tps("食飯"), "食飯");
+        assert_eq!(lomaji_to_tps("lí hó!"), "ㄌㄧˋ ㄏㄜˋ!");
+    }
+}
@@ -0,0 +1,113 @@
+//! Measures how long the engine takes to load and how much memory the engine
+//! holds afterwards, and compares the results with `targets.toml`.
+//!
+//! Run with `cargo make bench-startup` (release build). Each run measures a
+//! single load in a fresh process, so it reflects a cold start of the engine
+//! (though not of the OS file cache).
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use clap::Parser;
+use serde::Deserialize;
+
+use khiin::Engine;
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// Database file to load
+    #[arg(short, long, default_value = "resources/khiin.db")]
+    db_file: PathBuf,
+
+    /// Load the dictionary from this snapshot instead of the database
+    #[arg(short, long)]
+    snapshot_file: Option<PathBuf>,
+
+    #[arg(short, long, default_value = "khiin/bench/targets.toml")]
+    targets_file: PathBuf,
+
+    /// Exit with an error if a target is missed
+    #[arg(short, long)]
+    check: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct Target {
+    startup_ms: f64,
+    rss_mb: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Targets {
+    database: Target,
+    snapshot: Target,
+}
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+    let targets: Targets =
+        toml::from_str(&std::fs::read_to_string(&args.targets_file)?)?;
+
+    let rss_before = resident_mb();
+    let start = Instant::now();
+    let engine = match &args.snapshot_file {
+        Some(snapshot) => Engine::with_snapshot(&args.db_file, snapshot),
+        None => Engine::from_database(&args.db_file),
+    }
+    .ok_or_else(|| anyhow!("Unable to load engine"))?;
+    let startup_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let rss_after = resident_mb();
+
+    let (name, target) = match args.snapshot_file {
+        Some(_) => ("snapshot", &targets.snapshot),
+        None => ("database", &targets.database),
+    };
+
+    let mut ok = true;
+    println!("{:<12}{:>12}{:>12}", name, "measured", "target");
+    println!(
+        "{:<12}{:>12.1}{:>12.1}",
+        "startup ms", startup_ms, target.startup_ms
+    );
+    ok &= startup_ms <= target.startup_ms;
+
+    match (rss_before, rss_after) {
+        (Some(before), Some(after)) => {
+            println!("{:<12}{:>12.1}{:>12.1}", "rss mb", after, target.rss_mb);
+            println!("{:<12}{:>12.1}", "engine mb", after - before);
+            ok &= after <= target.rss_mb;
+        },
+        _ => println!("{:<12}{:>12}", "rss mb", "n/a"),
+    }
+
+    drop(engine);
+
+    if args.check && !ok {
+        println!("Startup targets missed");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Resident set size of this process in MiB
+#[cfg(target_os = "linux")]
+fn resident_mb() -> Option<f64> {
+    // Second field of statm is the resident page count
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: f64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // sysconf only reads a system setting
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    Some(pages * page_size as f64 / (1024.0 * 1024.0))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_mb() -> Option<f64> {
+    None
+}
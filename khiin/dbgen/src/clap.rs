@@ -14,6 +14,18 @@ pub struct Args {
 
     #[arg(short, long)]
     pub output_file: String,
+
+    /// Also write a precompiled dictionary snapshot to this file
+    #[arg(short, long)]
+    pub snapshot_file: Option<String>,
+
+    /// Optional CSV of glosses and variant markers for the conversions
+    #[arg(short, long)]
+    pub gloss_file: Option<String>,
+
+    /// Optional CSV of commonly used variants of standard Hanji
+    #[arg(short, long)]
+    pub variant_file: Option<String>,
 }
 
 impl Args {
@@ -30,6 +42,20 @@ impl Args {
             return Err(anyhow!("Frequency file {:?} not found.", path));
         }
 
+        if let Some(file) = &args.gloss_file {
+            let path = PathBuf::from(file);
+            if !path.exists() {
+                return Err(anyhow!("Gloss file {:?} not found.", path));
+            }
+        }
+
+        if let Some(file) = &args.variant_file {
+            let path = PathBuf::from(file);
+            if !path.exists() {
+                return Err(anyhow!("Variant file {:?} not found.", path));
+            }
+        }
+
         Ok(args)
     }
 }
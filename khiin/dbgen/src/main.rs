@@ -9,6 +9,7 @@ use simplelog::SimpleLogger;
 
 use khiin::db::CsvFiles;
 use khiin::db::Database;
+use khiin::Snapshot;
 
 use self::clap::Args;
 
@@ -16,20 +17,38 @@ pub fn main() -> Result<()> {
     SimpleLogger::init(log::LevelFilter::Debug, Config::default()).unwrap();
     log::debug!("Begin database generation");
 
-    let result = match Args::validate() {
-        Ok(args) => Database::from_csv(
-            &args.output_file,
-            CsvFiles::new(
-                read_to_cow(&args.frequency_file)?,
-                read_to_cow(&args.conversions_file)?,
-            ),
-        ),
+    let args = match Args::validate() {
+        Ok(args) => args,
         Err(e) => {
             log::error!("{}", e);
             return Err(e);
         },
     };
 
+    let mut csv_files = CsvFiles::new(
+        read_to_cow(&args.frequency_file)?,
+        read_to_cow(&args.conversions_file)?,
+    );
+
+    if let Some(file) = &args.gloss_file {
+        csv_files = csv_files.with_glosses(read_to_cow(file)?);
+    }
+
+    if let Some(file) = &args.variant_file {
+        csv_files = csv_files.with_variants(read_to_cow(file)?);
+    }
+
+    let result =
+        Database::from_csv(&args.output_file, csv_files).and_then(|db| {
+            match &args.snapshot_file {
+                Some(file) => {
+                    log::debug!("Writing dictionary snapshot to {}", file);
+                    Snapshot::write(&db, file)
+                },
+                None => Ok(()),
+            }
+        });
+
     if let Err(e) = result {
         log::error!("{}", e);
         return Err(e.into());
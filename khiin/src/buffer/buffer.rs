@@ -1,11 +1,17 @@
 use std::ops::Deref;
 use std::ops::DerefMut;
 
+use khiin_ji::ipa::lomaji_to_ipa;
+use khiin_ji::tps::lomaji_to_tps;
 use khiin_ji::IsHanji;
 
 use crate::buffer::BufferElement;
 use crate::buffer::BufferElementEnum;
 use crate::buffer::Spacer;
+use crate::config::AnnotationMode;
+
+/// Shown before the annotation of variant or non-standard characters
+const VARIANT_MARKER: &str = "※";
 
 #[derive(Default, Debug, Clone)]
 pub(crate) struct Buffer {
@@ -87,19 +93,98 @@ impl Buffer {
         })
     }
 
-    pub fn display_annotation(&self) -> String {
-        for elem in self.elems.iter() {
-            if let Some(conv) = elem.candidate() {
-                if let Some(anno) = conv.annotation.clone() {
-                    if anno.is_empty() {
-                        return anno;
-                    } else {
-                        return format!("    ({})", anno);
-                    }
-                }
-            }
+    pub fn display_annotation(&self, mode: AnnotationMode) -> String {
+        let text = match mode {
+            AnnotationMode::Hint => self.hint(),
+            AnnotationMode::Reading => self.reading().or_else(|| self.hint()),
+            AnnotationMode::Gloss => self.gloss().or_else(|| self.hint()),
+            AnnotationMode::Tps => self
+                .reading()
+                .map(|reading| lomaji_to_tps(&reading))
+                .or_else(|| self.hint()),
+            AnnotationMode::Ipa => self
+                .reading()
+                .map(|reading| lomaji_to_ipa(&reading))
+                .or_else(|| self.hint()),
+            AnnotationMode::Off => return String::new(),
+        };
+
+        let mut anno = match text {
+            Some(text) if !text.is_empty() => format!("({})", text),
+            _ => String::new(),
+        };
+
+        if self.is_variant() {
+            anno.insert_str(0, VARIANT_MARKER);
+        }
+
+        if anno.is_empty() {
+            anno
+        } else {
+            format!("    {}", anno)
         }
-        return String::new();
+    }
+
+    fn hint(&self) -> Option<String> {
+        self.elems
+            .iter()
+            .filter_map(|elem| elem.candidate())
+            .find_map(|conv| conv.annotation.clone())
+    }
+
+    /// The lomaji typed for each Hanji word, if there are any
+    fn reading(&self) -> Option<String> {
+        let readings: Vec<_> = self
+            .elems
+            .iter()
+            .filter_map(|elem| elem.candidate())
+            .filter(|conv| conv.output.chars().any(|ch| ch.is_hanji()))
+            .map(|conv| conv.input.as_str())
+            .collect();
+
+        if readings.is_empty() {
+            None
+        } else {
+            Some(readings.join(" "))
+        }
+    }
+
+    fn gloss(&self) -> Option<String> {
+        self.elems
+            .iter()
+            .filter_map(|elem| elem.candidate())
+            .find_map(|conv| conv.gloss.clone())
+    }
+
+    /// Whether any element is a variant or non-standard character
+    pub fn is_variant(&self) -> bool {
+        self.elems
+            .iter()
+            .any(|elem| elem.candidate().is_some_and(|conv| conv.variant))
+    }
+
+    /// Whether any element was found by correcting a typing mistake
+    pub fn is_fuzzy(&self) -> bool {
+        self.elems
+            .iter()
+            .any(|elem| elem.candidate().is_some_and(|conv| conv.fuzzy))
+    }
+
+    /// Whether any element completes the input typed so far
+    pub fn is_predicted(&self) -> bool {
+        self.elems
+            .iter()
+            .any(|elem| elem.candidate().is_some_and(|conv| conv.predicted))
+    }
+
+    /// The words in this buffer as displayed, leaving out punctuation and
+    /// other plain text
+    pub fn words(&self) -> Vec<String> {
+        self.elems
+            .iter()
+            .filter(|elem| matches!(elem, BufferElementEnum::KhiinElem(_)))
+            .map(|elem| elem.display_text())
+            .collect()
     }
 
     pub fn set_converted(&mut self, converted: bool) {
@@ -253,7 +338,9 @@ impl<'a> Iterator for BufferIter<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::buffer::KhiinElem;
     use crate::buffer::StringElem;
+    use crate::tests::mock_conversion;
 
     #[test]
     fn it_works() {
@@ -268,4 +355,40 @@ mod tests {
         buf.elems.push(el.into());
         assert_eq!(buf.raw_text().as_str(), "ho");
     }
+
+    #[test]
+    fn it_annotates_by_mode() {
+        let mut conv = mock_conversion("hó bô", "好無");
+        conv.annotation = Some("hint".into());
+        conv.gloss = Some("fine?".into());
+        let mut buf = Buffer::default();
+        buf.push(KhiinElem::from_conversion("hobo", &conv).unwrap().into());
+
+        assert_eq!(buf.display_annotation(AnnotationMode::Hint), "    (hint)");
+        assert_eq!(
+            buf.display_annotation(AnnotationMode::Reading),
+            "    (hó bô)"
+        );
+        assert_eq!(
+            buf.display_annotation(AnnotationMode::Gloss),
+            "    (fine?)"
+        );
+        assert_eq!(
+            buf.display_annotation(AnnotationMode::Tps),
+            "    (ㄏㄜˋ ㆠㄜˊ)"
+        );
+        assert_eq!(
+            buf.display_annotation(AnnotationMode::Ipa),
+            "    (hə˥˧ bə˨˦)"
+        );
+        assert_eq!(buf.display_annotation(AnnotationMode::Off), "");
+
+        conv.variant = true;
+        let mut buf = Buffer::default();
+        buf.push(KhiinElem::from_conversion("hobo", &conv).unwrap().into());
+        assert_eq!(
+            buf.display_annotation(AnnotationMode::Gloss),
+            "    ※(fine?)"
+        );
+    }
 }
@@ -6,9 +6,11 @@ use anyhow::anyhow;
 use anyhow::Result;
 
 use khiin_ji::lomaji::is_legal_lomaji;
+use khiin_ji::normalize::normalize;
 use khiin_ji::punctuation::get_hanji_chars;
 use khiin_ji::punctuation::get_lomaji_chars;
 use khiin_ji::IsHanji;
+use khiin_protos::command::candidate::Category;
 use khiin_protos::command::preedit::Segment;
 use khiin_protos::command::Candidate;
 use khiin_protos::command::CandidateList;
@@ -35,6 +37,9 @@ use crate::input::converter::get_candidates;
 use crate::input::converter::get_candidates_for_word;
 use crate::input::converter::get_candidates_for_word_with_tone;
 use crate::input::converter::get_numberic_tone_char;
+use crate::input::converter::is_number_input;
+use crate::input::converter::number_candidates;
+use crate::input::converter::snippet_candidates;
 use crate::utils::CharSubstr;
 
 use super::ActionElem;
@@ -63,6 +68,12 @@ pub(crate) struct BufferMgr {
 
     /// Whether the candidate is expanded
     cand_expanded: bool,
+
+    /// Last word committed, kept between edit sessions
+    last_word: Option<String>,
+
+    /// Words offered after the last commit
+    next_words: Vec<String>,
 }
 
 impl BufferMgr {
@@ -77,10 +88,12 @@ impl BufferMgr {
             cand_page: 0,
             pre_committed: String::new(),
             cand_expanded: false,
+            last_word: None,
+            next_words: Vec::new(),
         }
     }
 
-    pub fn build_preedit(&self) -> Preedit {
+    pub fn build_preedit(&self, conf: &Config) -> Preedit {
         let mut preedit = Preedit::default();
 
         let mut composing_segment = String::new();
@@ -137,11 +150,23 @@ impl BufferMgr {
             });
         }
 
-        preedit.caret = self.char_caret as i32;
+        // The caret counts chars, which change with the normalization
+        let form = conf.normalization();
+        let before_caret: String = preedit
+            .segments
+            .iter()
+            .flat_map(|s| s.value.chars())
+            .take(self.char_caret)
+            .collect();
+        preedit.caret = normalize(&before_caret, form).chars().count() as i32;
+        for segment in preedit.segments.iter_mut() {
+            segment.value = normalize(&segment.value, form);
+        }
+
         preedit
     }
 
-    pub fn get_candidates(&self) -> CandidateList {
+    pub fn get_candidates(&self, conf: &Config) -> CandidateList {
         let mut list = CandidateList::default();
 
         if self.edit_state == EditState::ES_CONVERTED {
@@ -150,9 +175,14 @@ impl BufferMgr {
 
         for (i, c) in self.candidates.iter().enumerate() {
             let mut cand = Candidate::default();
-            cand.value = c.display_text();
+            cand.value = normalize(&c.display_text(), conf.normalization());
             cand.id = i as i32;
-            cand.annotation = c.display_annotation();
+            cand.annotation = c.display_annotation(conf.annotation_mode());
+            if c.is_fuzzy() {
+                cand.category = Category::CCAT_FALLBACK.into();
+            } else if c.is_predicted() {
+                cand.category = Category::CCAT_PREDICTION.into();
+            }
             list.candidates.push(cand);
         }
 
@@ -163,10 +193,50 @@ impl BufferMgr {
         };
 
         list.page = self.cand_page as i32;
+        list.page_size = conf.page_size() as i32;
 
         list
     }
 
+    pub fn get_next_words(&self) -> CandidateList {
+        let mut list = CandidateList::default();
+
+        for (i, word) in self.next_words.iter().enumerate() {
+            let mut cand = Candidate::default();
+            cand.value = word.clone();
+            cand.id = i as i32;
+            list.candidates.push(cand);
+        }
+
+        list.focused = -1;
+        list
+    }
+
+    /// Words in the candidate that would be committed, or in the
+    /// composition if no candidate is focused
+    pub fn words_to_commit(&self) -> Vec<String> {
+        match self.focused_cand_idx.and_then(|i| self.candidates.get(i)) {
+            Some(candidate) => candidate.words(),
+            None => self.composition.words(),
+        }
+    }
+
+    pub fn last_word(&self) -> Option<&str> {
+        self.last_word.as_deref()
+    }
+
+    pub fn set_last_word(&mut self, word: Option<String>) {
+        self.last_word = word;
+    }
+
+    pub fn set_next_words(&mut self, words: Vec<String>) {
+        self.next_words = words;
+    }
+
+    pub fn next_word(&self, index: usize) -> Option<&str> {
+        self.next_words.get(index).map(|word| word.as_str())
+    }
+
     pub fn edit_state(&self) -> EditState {
         self.edit_state
     }
@@ -180,10 +250,12 @@ impl BufferMgr {
         self.cand_page = 0;
         self.pre_committed.clear();
         self.cand_expanded = false;
+        self.next_words.clear();
         Ok(())
     }
 
     pub fn insert(&mut self, engine: &EngInner, ch: char) -> Result<()> {
+        self.next_words.clear();
         match engine.conf.input_mode() {
             InputMode::Continuous => self.insert_continuous(engine, ch),
             InputMode::Classic => self.insert_classic(engine, ch),
@@ -228,7 +300,7 @@ impl BufferMgr {
         let mut index = match self.focused_cand_idx {
             Some(i) if i >= self.candidates.len() => 0,
             Some(i) => i,
-            None => self.cand_page * 9,
+            None => self.cand_page * engine.conf.page_size(),
         };
 
         self.composition.clear_autospace();
@@ -316,7 +388,7 @@ impl BufferMgr {
         let mut index = match self.focused_cand_idx {
             Some(i) if i >= self.candidates.len() => 0,
             Some(i) => i,
-            None => self.cand_page * 9,
+            None => self.cand_page * engine.conf.page_size(),
         };
 
         self.composition.clear_autospace();
@@ -417,6 +489,9 @@ impl BufferMgr {
             self.candidates.insert(0, first);
         }
 
+        let snippets = snippet_candidates(engine, &composition);
+        self.candidates.splice(0..0, snippets);
+
         for c in self.candidates.iter_mut() {
             c.autospace();
         }
@@ -480,11 +555,37 @@ impl BufferMgr {
     }
 
     fn build_composition_classic(
+        &mut self,
+        engine: &EngInner,
+        raw_input: String,
+        ch: char,
+    ) -> Result<()> {
+        self.build_candidates_classic(engine, raw_input, ch)?;
+
+        let snippets = snippet_candidates(engine, &self.composition.raw_text());
+        if !snippets.is_empty() {
+            self.candidates.splice(0..0, snippets);
+            self.reset_focus();
+        }
+        Ok(())
+    }
+
+    fn build_candidates_classic(
         &mut self,
         engine: &EngInner,
         mut raw_input: String,
         ch: char,
     ) -> Result<()> {
+        let mut number = raw_input.clone();
+        number.push(ch);
+        if is_number_input(&number) {
+            self.candidates = number_candidates(engine, &number);
+            self.composition = Buffer::new();
+            self.composition.push(StringElem::from(number).into());
+            self.char_caret = self.composition.display_char_count();
+            return Ok(());
+        }
+
         let mut key = ch.to_ascii_lowercase();
         if (key == engine.conf.hyphen()) {
             if engine.conf.khin_mode() == KhinMode::Hyphen {
@@ -1020,12 +1121,27 @@ impl BufferMgr {
 
         Ok(())
     }
+    /// Focuses the candidate with `id` from the candidate list, on any page
+    pub fn focus_candidate_by_id(
+        &mut self,
+        engine: &EngInner,
+        id: usize,
+    ) -> Result<()> {
+        if id >= self.candidates.len() {
+            return Err(anyhow!("No candidate {}", id));
+        }
+        if self.edit_state == EditState::ES_COMPOSING {
+            self.edit_state = EditState::ES_SELECTING;
+        }
+        self.focus_candidate(engine, id)
+    }
+
     pub fn focus_candidate_by_index(
         &mut self,
         engine: &EngInner,
         index: usize,
     ) -> Result<()> {
-        let mut to_focus = self.cand_page * 9 + index;
+        let mut to_focus = self.cand_page * engine.conf.page_size() + index;
         if to_focus >= self.candidates.len() {
             to_focus = self.candidates.len() - 1;
         }
@@ -1041,7 +1157,7 @@ impl BufferMgr {
         let mut to_focus = match self.focused_cand_idx {
             Some(i) if i >= self.candidates.len() - 1 => 0,
             Some(i) => i + 1,
-            None => self.cand_page * 9,
+            None => self.cand_page * engine.conf.page_size(),
         };
 
         self.focus_candidate(engine, to_focus);
@@ -1053,7 +1169,7 @@ impl BufferMgr {
         let mut to_focus = match self.focused_cand_idx {
             Some(i) if i == 0 => self.candidates.len() - 1,
             Some(i) => i - 1,
-            None => self.cand_page * 9,
+            None => self.cand_page * engine.conf.page_size(),
         };
 
         self.focus_candidate(engine, to_focus);
@@ -1064,14 +1180,15 @@ impl BufferMgr {
         &mut self,
         engine: &EngInner,
     ) -> Result<()> {
+        let page_size = engine.conf.page_size();
         if (self.candidates.is_empty()) {
             return Ok(());
         }
-        if (self.candidates.len() <= 9) {
+        if (self.candidates.len() <= page_size) {
             return Ok(());
         }
 
-        let total_page = (self.candidates.len() + 8) / 9;
+        let total_page = self.candidates.len().div_ceil(page_size);
         let to_page = if self.cand_page >= total_page - 1 {
             0
         } else {
@@ -1081,7 +1198,7 @@ impl BufferMgr {
         if self.focused_cand_idx.is_none() {
             self.cand_page = to_page;
         } else {
-            let mut to_focus = to_page * 9;
+            let mut to_focus = to_page * page_size;
             self.focus_candidate(engine, to_focus);
         }
         Ok(())
@@ -1091,14 +1208,15 @@ impl BufferMgr {
         &mut self,
         engine: &EngInner,
     ) -> Result<()> {
+        let page_size = engine.conf.page_size();
         if (self.candidates.is_empty()) {
             return Ok(());
         }
-        if (self.candidates.len() <= 9) {
+        if (self.candidates.len() <= page_size) {
             return Ok(());
         }
 
-        let total_page = (self.candidates.len() + 8) / 9;
+        let total_page = self.candidates.len().div_ceil(page_size);
         let to_page = if self.cand_page == 0 {
             total_page - 1
         } else {
@@ -1108,7 +1226,7 @@ impl BufferMgr {
         if self.focused_cand_idx.is_none() {
             self.cand_page = to_page;
         } else {
-            let mut to_focus = to_page * 9;
+            let mut to_focus = to_page * page_size;
             self.focus_candidate(engine, to_focus);
         }
         Ok(())
@@ -1159,7 +1277,7 @@ impl BufferMgr {
         self.composition = new_comp;
 
         self.focused_cand_idx = Some(index);
-        self.cand_page = index / 9;
+        self.cand_page = index / engine.conf.page_size();
         self.composition.autospace();
         self.char_caret = self.composition.display_char_count();
 
@@ -1188,7 +1306,7 @@ impl BufferMgr {
                 StringElem::from_raw_input(comp_raw.clone(), comp_raw).into(),
             );
             self.focused_cand_idx = Some(index);
-            self.cand_page = index / 9;
+            self.cand_page = index / engine.conf.page_size();
             self.char_caret = self.composition.display_char_count();
             return Ok(());
         }
@@ -1213,7 +1331,7 @@ impl BufferMgr {
             .push(StringElem::from_raw_input(comp_raw, candi_text).into());
 
         self.focused_cand_idx = Some(index);
-        self.cand_page = index / 9;
+        self.cand_page = index / engine.conf.page_size();
         self.char_caret = self.composition.display_char_count();
 
         Ok(())
@@ -1242,7 +1360,7 @@ impl BufferMgr {
 // Just for debugging
 impl BufferMgr {
     fn _debug_preedit(&self, sep: char) -> String {
-        let preedit = self.build_preedit();
+        let preedit = self.build_preedit(&Config::new());
         let mut display_text = String::new();
         let mut char_count = 0;
         display_text.push(sep);
@@ -1296,7 +1414,7 @@ impl Debug for BufferMgr {
 
         let mut display_text = self._debug_preedit(sep);
 
-        let cands = self.get_candidates();
+        let cands = self.get_candidates(&Config::new());
 
         if cands.candidates.is_empty() {
             return write!(f, "{}", display_text);
@@ -1320,11 +1438,12 @@ mod tests {
     use khiin_ji::contains_hanji;
 
     use super::*;
+    use crate::data::Snippets;
     use crate::tests::*;
     use crate::utils::Unique;
 
     fn preedit_text(buf: &BufferMgr) -> String {
-        let pe = buf.build_preedit();
+        let pe = buf.build_preedit(&Config::new());
         pe.segments.into_iter().map(|s| s.value).collect()
     }
 
@@ -1354,7 +1473,22 @@ mod tests {
         let text = preedit_text(&buf);
         assert_eq!(text.as_str(), "亞");
         assert_eq!(buf.focused_cand_idx, Some(0));
-        assert_eq!(buf.get_candidates().focused, 0);
+        assert_eq!(buf.get_candidates(&e.conf).focused, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn it_focuses_a_snippet_ahead_of_other_candidates() -> Result<()> {
+        let (mut e, mut buf) = test_harness();
+        e.conf.set_input_mode(InputMode::Continuous);
+        e.snippets =
+            Snippets::from_pairs([("a".to_string(), "台北市".to_string())])?;
+        buf.insert(&e, 'a')?;
+        buf.focus_next_candidate(&e)?;
+        assert_eq!(preedit_text(&buf).as_str(), "台北市");
+        assert_eq!(buf.get_candidates(&e.conf).focused, 0);
+        buf.focus_next_candidate(&e)?;
+        assert_eq!(preedit_text(&buf).as_str(), "亞");
         Ok(())
     }
 
@@ -1370,6 +1504,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn it_pages_by_the_configured_size() -> Result<()> {
+        let (mut e, mut buf) = test_harness();
+        e.conf.set_input_mode(InputMode::Continuous);
+        e.conf.set_page_size(5);
+        buf.insert(&e, 'a')?;
+        assert!(buf.candidates.len() > 5);
+
+        buf.show_next_page_candidate(&e)?;
+        let list = buf.get_candidates(&e.conf);
+        assert_eq!(list.page, 1);
+        assert_eq!(list.page_size, 5);
+
+        buf.focus_candidate_by_index(&e, 2)?;
+        assert_eq!(buf.focused_cand_idx, Some(7));
+        Ok(())
+    }
+
     #[test_log::test]
     fn it_excludes_duplicates() -> Result<()> {
         let (mut e, mut buf) = test_harness();
@@ -1414,6 +1566,31 @@ mod tests {
         Ok(())
     }
 
+    #[test_log::test]
+    fn it_offers_number_readings_classic() -> Result<()> {
+        let (mut e, mut buf) = test_harness();
+        e.conf.set_input_mode(InputMode::Classic);
+        e.conf.set_output_mode(crate::config::OutputMode::Hanji);
+
+        for ch in "3/15".chars() {
+            buf.insert(&e, ch)?;
+        }
+        assert_eq!(buf.composition.raw_text(), "3/15");
+        assert_eq!(buf.candidates[0].display_text(), "三月十五號");
+        assert_eq!(buf.candidates.last().unwrap().display_text(), "3/15");
+
+        buf.focus_candidate_by_index(&e, 0);
+        let committed = buf.commit_candidate_and_comosite_remainder(&e)?;
+        assert_eq!(committed, "三月十五號");
+
+        e.conf.set_output_mode(crate::config::OutputMode::Lomaji);
+        for ch in "$2".chars() {
+            buf.insert(&e, ch)?;
+        }
+        assert_eq!(buf.candidates[0].display_text(), "nn̄g kho͘");
+        Ok(())
+    }
+
     #[test_log::test]
     fn it_direct_commits_tilde_slash_backslash_classic() -> Result<()> {
         // '~', '\\', '/' must direct-output (no menu, no lingering compose);
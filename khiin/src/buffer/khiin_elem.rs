@@ -4,6 +4,7 @@ use regex::Regex;
 
 use crate::buffer::BufferElement;
 use crate::db::models::KeyConversion;
+use crate::input::fuzzy::align;
 use crate::input::Syllable;
 
 const SYL_SEPS: [char; 2] = ['-', ' '];
@@ -108,6 +109,34 @@ impl KhiinElem {
         }
     }
 
+    /// Sets the raw input of each syllable from the text actually typed, for
+    /// an element built from a corrected key sequence
+    pub fn realign(&mut self, typed: &str) {
+        let map = align(typed, &self.raw_text());
+        let mut start = 0;
+
+        for elem in self.value.iter_mut() {
+            if let Khiin::Syllable(syl) = elem {
+                let end = start + syl.raw_input.len();
+                syl.raw_input = typed[map[start]..map[end]].to_string();
+                start = end;
+            }
+        }
+    }
+
+    /// Sets the raw input of each syllable to one letter of `typed`, for an
+    /// element built from an abbreviation
+    pub fn realign_abbreviation(&mut self, typed: &str) {
+        let mut letters = typed.chars();
+
+        for elem in self.value.iter_mut() {
+            if let Khiin::Syllable(syl) = elem {
+                syl.raw_input =
+                    letters.next().map(String::from).unwrap_or_default();
+            }
+        }
+    }
+
     fn raw_caret_from_composed(&self, caret: usize) -> usize {
         0
     }
@@ -1,7 +1,11 @@
 pub(crate) mod conf;
 
+pub(crate) use conf::AnnotationMode;
 pub(crate) use conf::Config;
+pub(crate) use conf::HanjiVariant;
 pub(crate) use conf::InputMode;
+pub(crate) use conf::KeyboardLayout;
 pub(crate) use conf::ToneMode;
 pub(crate) use conf::OutputMode;
+pub(crate) use conf::ScriptPreference;
 pub(crate) use conf::KhinMode;
\ No newline at end of file
@@ -1,4 +1,13 @@
+use khiin_ji::ascii::KhinMark;
+use khiin_ji::ascii::Spelling;
+use khiin_ji::normalize::Normalization;
+
 use crate::db::models::InputType;
+use crate::input::fuzzy::default_confusions;
+use crate::input::fuzzy::Confusion;
+
+/// Digits 1-9 and 0 select from a page of candidates
+const MAX_PAGE_SIZE: usize = 10;
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum InputMode {
@@ -11,12 +20,18 @@ pub enum InputMode {
 pub enum ToneMode {
     Numeric,
     Telex,
+    /// Telex keys, with digits also accepted as tone keys
+    Hybrid,
 }
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum OutputMode {
     Lomaji,
     Hanji,
+    /// Taiwanese Phonetic Symbols, such as ㄐㄧㄚㆷ˙
+    Tps,
+    /// Lomaji in plain ASCII, with tone numbers, such as Tai5-gi2
+    Ascii,
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -26,11 +41,58 @@ pub enum KhinMode {
     Dot,
 }
 
-impl Into<InputType> for ToneMode {
-    fn into(self) -> InputType {
+/// Which form of a word is ranked first when Hanji is the output mode
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ScriptPreference {
+    /// Dictionary order
+    Any,
+    /// Hanji only, such as 食飯
+    Hanji,
+    /// Hanji mixed with lomaji, such as 食pn̄g
+    HanLo,
+}
+
+/// Which form of Hanji is written out
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum HanjiVariant {
+    /// As listed in the dictionary
+    Standard,
+    /// Commonly used variants from the variant table
+    Common,
+}
+
+/// How typed keys are read
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum KeyboardLayout {
+    /// Lomaji letters, with tone keys
+    Lomaji,
+    /// Taiwanese Phonetic Symbols on a zhuyin-style layout
+    Tps,
+}
+
+/// What is shown next to each candidate
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AnnotationMode {
+    /// Hints from the dictionary
+    Hint,
+    /// The lomaji reading of Hanji candidates
+    Reading,
+    /// A short English or Mandarin meaning
+    Gloss,
+    /// The reading of Hanji candidates in Taiwanese Phonetic Symbols
+    Tps,
+    /// The reading of Hanji candidates in IPA, with tone letters
+    Ipa,
+    Off,
+}
+
+impl ToneMode {
+    /// The key sequences that can be typed in this mode
+    pub fn input_types(&self) -> &'static [InputType] {
         match self {
-            ToneMode::Numeric => InputType::Numeric,
-            ToneMode::Telex => InputType::Telex,
+            ToneMode::Numeric => &[InputType::Numeric],
+            ToneMode::Telex => &[InputType::Telex],
+            ToneMode::Hybrid => &[InputType::Telex, InputType::Numeric],
         }
     }
 }
@@ -54,6 +116,24 @@ pub struct Config {
     output_mode: OutputMode,
     khin_mode: KhinMode,
     key_config: KeyConfig,
+    fuzzy_enabled: bool,
+    confusions: Vec<Confusion>,
+    prediction_enabled: bool,
+    prediction_limit: usize,
+    abbreviation_enabled: bool,
+    next_word_enabled: bool,
+    next_word_limit: usize,
+    autokhin: bool,
+    annotation_mode: AnnotationMode,
+    standard_only: bool,
+    hide_lomaji: bool,
+    script_preference: ScriptPreference,
+    hanji_variant: HanjiVariant,
+    page_size: usize,
+    keyboard_layout: KeyboardLayout,
+    ascii_spelling: Spelling,
+    ascii_khin: KhinMark,
+    normalization: Normalization,
 }
 
 impl Config {
@@ -76,6 +156,24 @@ impl Config {
                 hyphen: 'd',
                 done: 'r',
             },
+            fuzzy_enabled: false,
+            confusions: default_confusions(),
+            prediction_enabled: false,
+            prediction_limit: 3,
+            abbreviation_enabled: false,
+            next_word_enabled: false,
+            next_word_limit: 5,
+            autokhin: false,
+            annotation_mode: AnnotationMode::Hint,
+            standard_only: false,
+            hide_lomaji: false,
+            script_preference: ScriptPreference::Any,
+            hanji_variant: HanjiVariant::Standard,
+            page_size: 9,
+            keyboard_layout: KeyboardLayout::Lomaji,
+            ascii_spelling: Spelling::Poj,
+            ascii_khin: KhinMark::Hyphens,
+            normalization: Normalization::FontSafe,
         }
     }
 
@@ -163,6 +261,16 @@ impl Config {
         self.key_config.done
     }
 
+    /// The telex keys, whatever the current tone mode
+    pub fn key_config(&self) -> &KeyConfig {
+        &self.key_config
+    }
+
+    /// Whether `ch` is a digit tone key in hybrid mode
+    pub fn is_fallback_tone_digit(&self, ch: char) -> bool {
+        self.tone_mode == ToneMode::Hybrid && "2356789".contains(ch)
+    }
+
     pub fn is_reserved_char(&self, ch: char) -> bool {
         if ch == self.key_config.khin {
             true
@@ -185,7 +293,7 @@ impl Config {
         } else if ch == self.t9() {
             true
         } else {
-            false
+            self.is_fallback_tone_digit(ch)
         }
     }
 
@@ -205,7 +313,7 @@ impl Config {
         } else if ch == self.t9() {
             true
         } else {
-            false
+            self.is_fallback_tone_digit(ch)
         }
     }
 
@@ -214,7 +322,99 @@ impl Config {
     }
 
     pub fn is_lomaji_first(&self) -> bool {
-        self.output_mode == OutputMode::Lomaji
+        self.output_mode != OutputMode::Hanji
+    }
+
+    pub fn is_tps_output(&self) -> bool {
+        self.output_mode == OutputMode::Tps
+    }
+
+    pub fn is_ascii_output(&self) -> bool {
+        self.output_mode == OutputMode::Ascii
+    }
+
+    pub fn is_fuzzy_enabled(&self) -> bool {
+        self.fuzzy_enabled
+    }
+
+    pub(crate) fn confusions(&self) -> &[Confusion] {
+        &self.confusions
+    }
+
+    pub fn is_prediction_enabled(&self) -> bool {
+        self.prediction_enabled
+    }
+
+    /// Most completions offered for the input typed so far
+    pub fn prediction_limit(&self) -> usize {
+        self.prediction_limit
+    }
+
+    /// Whether the first letter of each syllable matches a word
+    pub fn is_abbreviation_enabled(&self) -> bool {
+        self.abbreviation_enabled
+    }
+
+    pub fn is_next_word_enabled(&self) -> bool {
+        self.next_word_enabled
+    }
+
+    /// Most next words offered after a commit
+    pub fn next_word_limit(&self) -> usize {
+        self.next_word_limit
+    }
+
+    /// Whether to use the khin forms listed in the dictionary without the
+    /// khin key being typed
+    pub fn is_autokhin(&self) -> bool {
+        self.autokhin
+    }
+
+    pub fn annotation_mode(&self) -> AnnotationMode {
+        self.annotation_mode
+    }
+
+    /// Whether to leave out candidates marked as variant or non-standard
+    /// characters, keeping the MOE-recommended forms. Only conversions given
+    /// a `variant` flag in the gloss data are marked, so the settings app
+    /// does not offer this yet.
+    pub fn is_standard_only(&self) -> bool {
+        self.standard_only
+    }
+
+    /// Whether to leave out lomaji-only candidates when Hanji is the output
+    /// mode
+    pub fn is_lomaji_hidden(&self) -> bool {
+        self.hide_lomaji
+    }
+
+    pub fn script_preference(&self) -> ScriptPreference {
+        self.script_preference
+    }
+
+    pub fn hanji_variant(&self) -> HanjiVariant {
+        self.hanji_variant
+    }
+
+    /// Candidates shown on each page, at most one per selection digit
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    pub fn keyboard_layout(&self) -> KeyboardLayout {
+        self.keyboard_layout
+    }
+
+    pub fn ascii_spelling(&self) -> Spelling {
+        self.ascii_spelling
+    }
+
+    pub fn ascii_khin(&self) -> KhinMark {
+        self.ascii_khin
+    }
+
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
     }
 
     pub fn is_khinless(&self) -> bool {
@@ -241,6 +441,78 @@ impl Config {
         self.khin_mode = mode;
     }
 
+    pub fn set_fuzzy_enabled(&mut self, enabled: bool) {
+        self.fuzzy_enabled = enabled;
+    }
+
+    pub(crate) fn set_confusions(&mut self, confusions: Vec<Confusion>) {
+        self.confusions = confusions;
+    }
+
+    pub fn set_prediction_enabled(&mut self, enabled: bool) {
+        self.prediction_enabled = enabled;
+    }
+
+    pub fn set_prediction_limit(&mut self, limit: usize) {
+        self.prediction_limit = limit;
+    }
+
+    pub fn set_abbreviation_enabled(&mut self, enabled: bool) {
+        self.abbreviation_enabled = enabled;
+    }
+
+    pub fn set_next_word_enabled(&mut self, enabled: bool) {
+        self.next_word_enabled = enabled;
+    }
+
+    pub fn set_next_word_limit(&mut self, limit: usize) {
+        self.next_word_limit = limit;
+    }
+
+    pub fn set_autokhin(&mut self, enabled: bool) {
+        self.autokhin = enabled;
+    }
+
+    pub fn set_annotation_mode(&mut self, mode: AnnotationMode) {
+        self.annotation_mode = mode;
+    }
+
+    pub fn set_standard_only(&mut self, enabled: bool) {
+        self.standard_only = enabled;
+    }
+
+    pub fn set_lomaji_hidden(&mut self, hidden: bool) {
+        self.hide_lomaji = hidden;
+    }
+
+    pub fn set_script_preference(&mut self, preference: ScriptPreference) {
+        self.script_preference = preference;
+    }
+
+    pub fn set_hanji_variant(&mut self, variant: HanjiVariant) {
+        self.hanji_variant = variant;
+    }
+
+    pub fn set_page_size(&mut self, size: usize) {
+        self.page_size = size.clamp(1, MAX_PAGE_SIZE);
+    }
+
+    pub fn set_keyboard_layout(&mut self, layout: KeyboardLayout) {
+        self.keyboard_layout = layout;
+    }
+
+    pub fn set_ascii_spelling(&mut self, spelling: Spelling) {
+        self.ascii_spelling = spelling;
+    }
+
+    pub fn set_ascii_khin(&mut self, khin: KhinMark) {
+        self.ascii_khin = khin;
+    }
+
+    pub fn set_normalization(&mut self, form: Normalization) {
+        self.normalization = form;
+    }
+
     // set key_config
     pub fn set_t2_key(&mut self, t2: char) {
         self.key_config.t2 = t2;
@@ -1,9 +1,15 @@
 pub(crate) mod dictionary;
 pub(crate) mod segmenter;
+pub(crate) mod snapshot;
+pub(crate) mod snippets;
 pub(crate) mod trie;
 pub(crate) mod syllable_trie;
+pub(crate) mod variants;
 
 pub(crate) use dictionary::Dictionary;
 pub(crate) use segmenter::Segmenter;
+pub(crate) use snapshot::Snapshot;
+pub(crate) use snippets::Snippets;
 pub(crate) use trie::Trie;
-pub(crate) use syllable_trie::SyllableTrie;
\ No newline at end of file
+pub(crate) use syllable_trie::SyllableTrie;
+pub(crate) use variants::HanjiVariants;
\ No newline at end of file
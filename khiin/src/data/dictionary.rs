@@ -1,33 +1,54 @@
 use anyhow::Result;
+use once_cell::sync::Lazy;
 
 use crate::config::ToneMode;
+use crate::data::trie::TrieBuilder;
 use crate::data::Segmenter;
+use crate::data::Snapshot;
 use crate::data::Trie;
 use crate::data::SyllableTrie;
 use crate::db::Database;
 
+/// The syllable list does not depend on the tone mode or the database, so one
+/// copy is shared by every dictionary.
+static SYLLABLE_TRIE: Lazy<SyllableTrie> = Lazy::new(SyllableTrie::new);
+
 pub(crate) struct Dictionary {
     word_trie: Trie,
-    syllable_trie: SyllableTrie,
     segmenter: Segmenter,
 }
 
 impl Dictionary {
     pub fn new(db: &Database, tone_mode: ToneMode) -> Result<Self> {
         log::debug!("Initializing Dictionary");
-        let inputs = db.select_all_words_by_freq(tone_mode.into())?;
-        log::debug!("Database query successful");
-
-        let word_trie = Trie::new(&inputs)?;
+        let mut builder = TrieBuilder::new();
+        db.for_each_word_by_freq(tone_mode.input_types(), |word| {
+            builder.push(&word)
+        })?;
+        let word_trie = builder.build();
         log::debug!("Word trie loaded");
-        let syllable_trie = SyllableTrie::new();
-        log::debug!("Syllable trie loaded");
-        let segmenter = Segmenter::new(inputs)?;
-        log::debug!("Segmenter loaded");
+        let segmenter = Segmenter::new(word_trie.max_word_length());
+
+        Ok(Self {
+            word_trie,
+            segmenter,
+        })
+    }
+
+    pub fn from_snapshot(
+        snapshot: &Snapshot,
+        tone_mode: ToneMode,
+    ) -> Result<Self> {
+        let sections = tone_mode
+            .input_types()
+            .iter()
+            .map(|&input_type| snapshot.section(input_type))
+            .collect::<Result<Vec<_>>>()?;
+        let word_trie = Trie::from_snapshot(sections);
+        let segmenter = Segmenter::new(word_trie.max_word_length());
 
         Ok(Self {
             word_trie,
-            syllable_trie,
             segmenter,
         })
     }
@@ -40,16 +61,29 @@ impl Dictionary {
         self.word_trie.find_words_from_start(query)
     }
 
+    pub fn contains_word(&self, query: &str) -> bool {
+        self.word_trie.contains(query)
+    }
+
+    pub fn find_words_near(
+        &self,
+        query: &str,
+        max_edits: usize,
+    ) -> Vec<(usize, String)> {
+        self.word_trie.find_words_near(query, max_edits)
+    }
+
     pub fn is_legal_syllable_prefix(&self, query: &str) -> bool {
-        self.syllable_trie.is_valid_prefix(query)  
+        SYLLABLE_TRIE.is_valid_prefix(query)
     }
 
     pub fn is_legal_syllable(&self, query: &str) -> bool {
-        self.syllable_trie.is_valid_syllable(query)  
+        SYLLABLE_TRIE.is_valid_syllable(query)
     }
 
     pub fn segment(&self, query: &str) -> Result<Vec<String>> {
-        self.segmenter.segment(query)
+        self.segmenter
+            .segment(query, |word| self.word_trie.cost(word))
     }
 
     pub fn can_segment(&self, query: &str) -> bool {
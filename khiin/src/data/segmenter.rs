@@ -1,9 +1,6 @@
-use std::collections::HashMap;
-
 use anyhow::Result;
 use bit_vec::BitVec;
 
-use crate::db::models::KeySequence;
 
 /// A number 0.0 or greater. If set to 0.0, all words will be treated equally
 /// regardless of frequency. The higher the number, the more heavily weighted
@@ -29,86 +26,38 @@ const SYLLABLE_COUNT_BIAS: f64 = 0.2;
 
 const BIG: f64 = 1e10;
 
+/// Splits input into words. Word costs come from the dictionary's word trie
+/// (see `Trie::cost`), so that keys are not stored twice.
 pub struct Segmenter {
     max_word_length: usize,
-    cost_map: HashMap<String, f64>,
 }
 
-fn min_max(map: &HashMap<String, f64>) -> Option<(f64, f64)> {
-    let mut min: Option<f64> = None;
-    let mut max: Option<f64> = None;
-
-    for &value in map.values() {
-        if let Some(current_min) = min {
-            if let Some(cmp) = current_min.partial_cmp(&value) {
-                if cmp == std::cmp::Ordering::Greater {
-                    min = Some(value);
-                }
-            }
-        } else {
-            min = Some(value);
-        }
-
-        if let Some(current_max) = max {
-            if let Some(cmp) = current_max.partial_cmp(&value) {
-                if cmp == std::cmp::Ordering::Less {
-                    max = Some(value);
-                }
-            }
-        } else {
-            max = Some(value);
-        }
-    }
-
-    match (min, max) {
-        (Some(min_value), Some(max_value)) => Some((min_value, max_value)),
-        _ => None,
-    }
+/// The segmentation cost of a word with `word_len` chars, `n_syls` syllables
+/// and frequency `p`
+pub(crate) fn word_cost(word_len: usize, n_syls: usize, p: f64) -> f64 {
+    let p = if p <= 0.0 {
+        1e-5 / 10f64.powf(word_len as f64)
+    } else {
+        p
+    };
+
+    // Apply the cost biases
+    let cost = (1.0 / p.powf(FREQUENCY_BIAS)).ln();
+    let bias = (word_len as f64).powf(LETTER_COUNT_BIAS);
+    let syl_bias = (n_syls as f64).powf(SYLLABLE_COUNT_BIAS);
+    cost / bias * syl_bias
 }
 
 impl Segmenter {
-    pub fn new(words_by_frequency: Vec<KeySequence>) -> Result<Self> {
-        let mut max_word_length = 0;
-        let mut cost_map = HashMap::new();
-
-        for word in words_by_frequency.into_iter() {
-            if cost_map.contains_key(&word.keys) {
-                continue;
-            }
-
-            let word_len = word.keys.chars().count();
-            max_word_length = std::cmp::max(max_word_length, word_len);
-
-            let p = if word.p <= 0.0 {
-                1e-5 / 10f64.powf(word_len as f64)
-            } else {
-                word.p
-            };
-
-            // Apply the cost biases
-            let mut cost = (1.0 / p.powf(FREQUENCY_BIAS)).ln();
-            let bias = (word_len as f64).powf(LETTER_COUNT_BIAS);
-            let syl_bias = (word.n_syls as f64).powf(SYLLABLE_COUNT_BIAS);
-            cost = cost / bias * syl_bias;
-            cost_map.insert(word.keys, cost);
-        }
-
-        if let Some((min, max)) = min_max(&cost_map) {
-            log::debug!("min {}, max {}", min, max);
-        }
-
-        Ok(Segmenter {
-            max_word_length,
-            cost_map,
-        })
+    pub fn new(max_word_length: usize) -> Self {
+        Segmenter { max_word_length }
     }
 
-    pub fn segment(&self, input: &str) -> Result<Vec<String>> {
-        Ok(segment_min_cost(
-            input,
-            &self.cost_map,
-            self.max_word_length,
-        ))
+    pub fn segment<T>(&self, input: &str, cost_of: T) -> Result<Vec<String>>
+    where
+        T: Fn(&str) -> Option<f64>,
+    {
+        Ok(segment_min_cost(input, cost_of, self.max_word_length))
     }
 
     pub fn can_segment<T>(is_word: T, query: &str) -> bool
@@ -189,11 +138,14 @@ where
 ///
 /// where `𝓟` is the number of occurrences of a word in the corpus divided by
 /// the total number of words in the corpus. This seems to give decent results.
-fn segment_min_cost(
+fn segment_min_cost<T>(
     input: &str,
-    cost_map: &HashMap<String, f64>,
+    cost_of: T,
     max_word_len: usize,
-) -> Vec<String> {
+) -> Vec<String>
+where
+    T: Fn(&str) -> Option<f64>,
+{
     let len = input.chars().count();
     let mut costs: Vec<(f64, i32)> = Vec::new();
     costs.push((0.0, -1));
@@ -210,13 +162,14 @@ fn segment_min_cost(
 
             log::debug!("chunk: {}", chunk);
 
-            if !cost_map.contains_key(chunk) {
-                continue;
-            }
+            let cost = match cost_of(chunk) {
+                Some(cost) => cost,
+                None => continue,
+            };
 
-            log::debug!("chunk cost: {}", cost_map.get(chunk).unwrap());
+            log::debug!("chunk cost: {}", cost);
 
-            curr_cost = costs[j].0 + cost_map.get(chunk).unwrap();
+            curr_cost = costs[j].0 + cost;
             if curr_cost <= min_cost {
                 min_cost = curr_cost;
                 min_cost_idx = j;
@@ -255,7 +208,9 @@ fn segment_min_cost(
 mod tests {
     use std::collections::HashMap;
 
+    use crate::data::Trie;
     use crate::db::models::InputType;
+    use crate::db::models::KeySequence;
 
     use crate::collection;
 
@@ -272,7 +227,8 @@ mod tests {
             "png".into() => 40.0,
         );
         let max_word_len = 5;
-        let result = segment_min_cost(input, &cost_map, max_word_len);
+        let result =
+            segment_min_cost(input, |s| cost_map.get(s).copied(), max_word_len);
         assert_eq!(result.len(), 4);
     }
 
@@ -318,15 +274,19 @@ mod tests {
             p: 0.01,
         })
         .collect();
-        let segmenter =
-            Segmenter::new(words).expect("Could not build segmenter");
+        let trie = Trie::new(&words).expect("Could not build trie");
+        let segmenter = Segmenter::new(trie.max_word_length());
+        let cost_of = |word: &str| trie.cost(word);
         let result = segmenter
-            .segment("goamchaiujoachelanghamgoaukangkhoanesengtiong")
+            .segment("goamchaiujoachelanghamgoaukangkhoanesengtiong", cost_of)
             .expect("Could not segment text");
         log::debug!("{}", result.join(" "));
         assert_eq!(result.len(), 12);
         let result = segmenter
-            .segment("goa2mchaiu7joa7che7lang5ham5goa2ukangkhoan2esengtiong")
+            .segment(
+                "goa2mchaiu7joa7che7lang5ham5goa2ukangkhoan2esengtiong",
+                cost_of,
+            )
             .expect("Could not segment text");
         log::debug!("{}", result.join(" "));
         assert_eq!(result.len(), 12);
@@ -0,0 +1,470 @@
+//! Precompiled dictionary snapshot.
+//!
+//! Building the word trie and the segmenter cost map means reading every key
+//! sequence from the database and computing its cost, which dominates engine
+//! startup. `dbgen` can instead write these structures to a flat file once,
+//! which the engine memory-maps and searches in place.
+//!
+//! All integers are little-endian. The layout is:
+//!
+//! ```text
+//! header    magic "KHIINSNP", version: u32, section_count: u32
+//! sections  section_count * { input_type: u32, offset: u64, len: u64 }
+//!
+//! section   max_word_length: u32, entry_count: u32,
+//!           entries: entry_count * {
+//!               key_offset: u32, key_len: u32,
+//!               ids_offset: u32, ids_len: u32, cost: f64
+//!           },
+//!           ids: [i64], keys: [u8]
+//! ```
+//!
+//! Entries are sorted by key, so lookups are binary searches and all keys
+//! sharing a prefix are contiguous. Offsets in an entry are relative to the
+//! start of the `ids` and `keys` arrays respectively.
+//!
+//! Conversions are not part of the snapshot. Their lookups rank outputs
+//! against the user's unigram and bigram counts, which live in the SQLite
+//! database, so the engine still restores the database into memory. The
+//! snapshot saves startup time rather than memory.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::ops::Deref;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use memmap2::Mmap;
+
+use crate::data::segmenter::word_cost;
+use crate::db::models::InputType;
+use crate::db::Database;
+
+const MAGIC: &[u8; 8] = b"KHIINSNP";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 16;
+const SECTION_HEADER_LEN: usize = 20;
+const ENTRY_LEN: usize = 24;
+
+enum Storage {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Storage::Mapped(mmap) => mmap,
+            Storage::Owned(vec) => vec,
+        }
+    }
+}
+
+pub struct Snapshot {
+    data: Arc<Storage>,
+    sections: Vec<(InputType, Range<usize>)>,
+}
+
+impl Snapshot {
+    /// Memory-maps the snapshot file at `path`.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path)?;
+        // The file is written once by dbgen and never modified in place
+        let mmap = unsafe { Mmap::map(&file)? };
+        Self::from_storage(Storage::Mapped(mmap))
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_storage(Storage::Owned(bytes))
+    }
+
+    fn from_storage(storage: Storage) -> Result<Self> {
+        let data = Arc::new(storage);
+
+        if data.len() < HEADER_LEN || &data[0..8] != MAGIC {
+            return Err(anyhow!("Not a khiin dictionary snapshot"));
+        }
+
+        let version = read_u32(&data, 8);
+        if version != VERSION {
+            return Err(anyhow!("Unsupported snapshot version {}", version));
+        }
+
+        let count = read_u32(&data, 12) as usize;
+        count
+            .checked_mul(SECTION_HEADER_LEN)
+            .and_then(|len| len.checked_add(HEADER_LEN))
+            .filter(|&end| end <= data.len())
+            .ok_or_else(truncated)?;
+        let mut sections = Vec::with_capacity(count);
+
+        for i in 0..count {
+            let pos = HEADER_LEN + i * SECTION_HEADER_LEN;
+            let input_type = match read_u32(&data, pos) {
+                0 => InputType::Detoned,
+                1 => InputType::Numeric,
+                2 => InputType::Telex,
+                ty => return Err(anyhow!("Unknown input type {}", ty)),
+            };
+            let start = usize::try_from(read_u64(&data, pos + 4))?;
+            let len = usize::try_from(read_u64(&data, pos + 12))?;
+            let end = start
+                .checked_add(len)
+                .filter(|&end| end <= data.len())
+                .ok_or_else(truncated)?;
+
+            sections.push((input_type, start..end));
+        }
+
+        Ok(Self { data, sections })
+    }
+
+    pub(crate) fn section(
+        &self,
+        input_type: InputType,
+    ) -> Result<SnapshotSection> {
+        let range = self
+            .sections
+            .iter()
+            .find(|(ty, _)| *ty == input_type)
+            .map(|(_, range)| range.clone())
+            .ok_or_else(|| {
+                anyhow!("No {:?} section in snapshot", input_type)
+            })?;
+
+        SnapshotSection::new(self.data.clone(), range)
+    }
+
+    /// Writes a snapshot with the word index of each tone mode in `db`.
+    pub fn write<P>(db: &Database, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let mut sections = Vec::new();
+        for input_type in [InputType::Numeric, InputType::Telex] {
+            sections.push((input_type, build_section(db, input_type)?));
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+
+        let mut offset = HEADER_LEN + sections.len() * SECTION_HEADER_LEN;
+        for (input_type, section) in sections.iter() {
+            out.extend_from_slice(&(*input_type as u32).to_le_bytes());
+            out.extend_from_slice(&(offset as u64).to_le_bytes());
+            out.extend_from_slice(&(section.len() as u64).to_le_bytes());
+            offset += section.len();
+        }
+
+        for (_, section) in sections.iter() {
+            out.extend_from_slice(section);
+        }
+
+        let mut file = File::create(path)?;
+        file.write_all(&out)?;
+        Ok(())
+    }
+}
+
+fn build_section(db: &Database, input_type: InputType) -> Result<Vec<u8>> {
+    let words = db.select_all_words_by_freq(&[input_type])?;
+
+    // Same rules as `Trie::new` and `Segmenter::new`: ids are kept in
+    // frequency order, and the first (most frequent) cost wins.
+    let mut entries: BTreeMap<&str, (f64, Vec<i64>)> = BTreeMap::new();
+    let mut max_word_length = 0;
+
+    for word in words.iter() {
+        let len = word.keys.chars().count();
+        max_word_length = std::cmp::max(max_word_length, len);
+        entries
+            .entry(word.keys.as_str())
+            .or_insert_with(|| (word_cost(len, word.n_syls, word.p), vec![]))
+            .1
+            .push(word.input_id);
+    }
+
+    let mut table = Vec::with_capacity(entries.len() * ENTRY_LEN);
+    let mut ids = Vec::new();
+    let mut keys = Vec::new();
+    let mut n_ids = 0u32;
+
+    for (key, (cost, key_ids)) in entries.iter() {
+        table.extend_from_slice(&(keys.len() as u32).to_le_bytes());
+        table.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        table.extend_from_slice(&n_ids.to_le_bytes());
+        table.extend_from_slice(&(key_ids.len() as u32).to_le_bytes());
+        table.extend_from_slice(&cost.to_le_bytes());
+        keys.extend_from_slice(key.as_bytes());
+        for id in key_ids {
+            ids.extend_from_slice(&id.to_le_bytes());
+        }
+        n_ids += key_ids.len() as u32;
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&(max_word_length as u32).to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    out.extend_from_slice(&table);
+    out.extend_from_slice(&ids);
+    out.extend_from_slice(&keys);
+    Ok(out)
+}
+
+/// The word index of a single tone mode, searched in place.
+#[derive(Clone)]
+pub(crate) struct SnapshotSection {
+    data: Arc<Storage>,
+    entries: usize,
+    ids: usize,
+    keys: usize,
+    len: usize,
+    max_word_length: usize,
+}
+
+impl SnapshotSection {
+    fn new(data: Arc<Storage>, range: Range<usize>) -> Result<Self> {
+        if range.len() < 8 {
+            return Err(anyhow!("Snapshot section is truncated"));
+        }
+
+        let max_word_length = read_u32(&data, range.start) as usize;
+        let len = read_u32(&data, range.start + 4) as usize;
+        let entries = range.start + 8;
+        let ids = len
+            .checked_mul(ENTRY_LEN)
+            .and_then(|size| size.checked_add(entries))
+            .filter(|&ids| ids <= range.end)
+            .ok_or_else(truncated)?;
+
+        let mut n_ids = 0;
+        if len > 0 {
+            let last = entries + (len - 1) * ENTRY_LEN;
+            n_ids = (read_u32(&data, last + 8) as usize)
+                .checked_add(read_u32(&data, last + 12) as usize)
+                .ok_or_else(truncated)?;
+        }
+        let keys = n_ids
+            .checked_mul(8)
+            .and_then(|size| size.checked_add(ids))
+            .filter(|&keys| keys <= range.end)
+            .ok_or_else(truncated)?;
+
+        // Checked once here, so lookups can index without checks
+        for index in 0..len {
+            let pos = entries + index * ENTRY_LEN;
+            let key = checked_range(&data, pos, keys, range.end)?;
+            if std::str::from_utf8(&data[key]).is_err() {
+                return Err(anyhow!("Snapshot key is not valid UTF-8"));
+            }
+            checked_range(&data, pos + 8, 0, n_ids)?;
+        }
+
+        Ok(Self {
+            data,
+            entries,
+            ids,
+            keys,
+            len,
+            max_word_length,
+        })
+    }
+
+    pub fn max_word_length(&self) -> usize {
+        self.max_word_length
+    }
+
+    fn key(&self, index: usize) -> &str {
+        let pos = self.entries + index * ENTRY_LEN;
+        let start = self.keys + read_u32(&self.data, pos) as usize;
+        let len = read_u32(&self.data, pos + 4) as usize;
+        // Validated in `new`
+        std::str::from_utf8(&self.data[start..start + len]).unwrap_or_default()
+    }
+
+    fn cost(&self, index: usize) -> f64 {
+        let pos = self.entries + index * ENTRY_LEN + 16;
+        f64::from_le_bytes(self.data[pos..pos + 8].try_into().unwrap())
+    }
+
+    fn ids(&self, index: usize) -> impl Iterator<Item = i64> + '_ {
+        let pos = self.entries + index * ENTRY_LEN;
+        let start = self.ids + read_u32(&self.data, pos + 8) as usize * 8;
+        let len = read_u32(&self.data, pos + 12) as usize;
+        (0..len).map(move |i| read_i64(&self.data, start + i * 8))
+    }
+
+    /// Index of the first key that is not less than `query`
+    fn lower_bound(&self, query: &str) -> usize {
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.key(mid) < query {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    fn find(&self, query: &str) -> Option<usize> {
+        let i = self.lower_bound(query);
+        (i < self.len && self.key(i) == query).then_some(i)
+    }
+
+    pub fn contains(&self, query: &str) -> bool {
+        self.find(query).is_some()
+    }
+
+    pub fn has_prefix(&self, prefix: &str) -> bool {
+        let i = self.lower_bound(prefix);
+        i < self.len && self.key(i).starts_with(prefix)
+    }
+
+    pub fn get_cost(&self, query: &str) -> Option<f64> {
+        self.find(query).map(|i| self.cost(i))
+    }
+
+    pub fn keys_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        (self.lower_bound(prefix)..self.len)
+            .map(|i| self.key(i))
+            .take_while(move |key| key.starts_with(prefix))
+    }
+
+    pub fn ids_with_prefix(&self, prefix: &str) -> Vec<i64> {
+        let mut result = Vec::new();
+        let mut i = self.lower_bound(prefix);
+        while i < self.len && self.key(i).starts_with(prefix) {
+            result.extend(self.ids(i));
+            i += 1;
+        }
+        result
+    }
+}
+
+fn truncated() -> anyhow::Error {
+    anyhow!("Snapshot is truncated")
+}
+
+/// The range given by the `(offset, len)` pair at `pos`, offset from `base`,
+/// if it ends within `limit`
+fn checked_range(
+    data: &[u8],
+    pos: usize,
+    base: usize,
+    limit: usize,
+) -> Result<Range<usize>> {
+    let start = base
+        .checked_add(read_u32(data, pos) as usize)
+        .ok_or_else(truncated)?;
+    let end = start
+        .checked_add(read_u32(data, pos + 4) as usize)
+        .filter(|&end| end <= limit)
+        .ok_or_else(truncated)?;
+    Ok(start..end)
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+fn read_i64(data: &[u8], pos: usize) -> i64 {
+    i64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ToneMode;
+    use crate::data::Dictionary;
+    use crate::tests::*;
+
+    fn get_snapshot() -> Snapshot {
+        let path = std::env::temp_dir().join("khiin_test.snapshot");
+        Snapshot::write(&get_db(), &path).unwrap();
+        Snapshot::open(&path).unwrap()
+    }
+
+    #[test]
+    fn it_rejects_other_files() {
+        assert!(Snapshot::from_bytes(b"SQLite format 3".to_vec()).is_err());
+    }
+
+    #[test]
+    fn it_rejects_truncated_or_corrupt_snapshots() -> Result<()> {
+        let path = std::env::temp_dir().join("khiin_corrupt_test.snapshot");
+        Snapshot::write(&get_db(), &path)?;
+        let bytes = std::fs::read(&path)?;
+        let section_start = read_u64(&bytes, HEADER_LEN + 4) as usize;
+
+        let truncated = bytes[..bytes.len() - 1].to_vec();
+        assert!(Snapshot::from_bytes(truncated).is_err());
+
+        let mut overflowing = bytes.clone();
+        overflowing[HEADER_LEN + 12..HEADER_LEN + 20]
+            .copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Snapshot::from_bytes(overflowing).is_err());
+
+        // Point the first key past the end of the section
+        let mut bad_key = bytes.clone();
+        let key_pos = section_start + 8;
+        bad_key[key_pos..key_pos + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let snapshot = Snapshot::from_bytes(bad_key)?;
+        assert!(snapshot.section(InputType::Numeric).is_err());
+
+        let snapshot = Snapshot::from_bytes(bytes)?;
+        assert!(snapshot.section(InputType::Numeric).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn it_matches_the_database_dictionary() -> Result<()> {
+        let snapshot = get_snapshot();
+        let db = get_db();
+
+        for tone_mode in [ToneMode::Numeric, ToneMode::Telex, ToneMode::Hybrid]
+        {
+            let built = Dictionary::new(&db, tone_mode)?;
+            let loaded = Dictionary::from_snapshot(&snapshot, tone_mode)?;
+
+            for query in ["goa", "ho2", "hof", "chh", "e"] {
+                assert_eq!(
+                    built.find_words_by_prefix(query),
+                    loaded.find_words_by_prefix(query)
+                );
+            }
+
+            let input = "lihopengangoutuitiunnkinkukasiok";
+            assert_eq!(built.segment(input)?, loaded.segment(input)?);
+            assert_eq!(
+                built.all_words_from_start("chhiahpng"),
+                loaded.all_words_from_start("chhiahpng")
+            );
+            assert_eq!(
+                built.can_segment_max("goaxyz"),
+                loaded.can_segment_max("goaxyz")
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+/// User-defined text offered when its trigger is typed, e.g. an address for
+/// `addr`. Triggers are matched case-insensitively. The snippets are stored
+/// in the user database; this keeps a copy for lookups while typing.
+#[derive(Default)]
+pub(crate) struct Snippets {
+    values: BTreeMap<String, Vec<String>>,
+}
+
+impl Snippets {
+    /// Builds the snippets from `(trigger, value)` pairs, failing on the first
+    /// pair with an empty trigger or value
+    pub fn from_pairs<I>(pairs: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut snippets = Self::default();
+        snippets.extend(pairs)?;
+        Ok(snippets)
+    }
+
+    /// The `(trigger, value)` pairs with lowercase triggers, as they are
+    /// stored, failing if any pair has an empty trigger or value
+    pub fn normalize<I>(pairs: I) -> Result<Vec<(String, String)>>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        pairs
+            .into_iter()
+            .map(|(trigger, value)| {
+                if trigger.is_empty() || value.is_empty() {
+                    Err(anyhow!("Snippets need a trigger and a value"))
+                } else {
+                    Ok((trigger.to_ascii_lowercase(), value))
+                }
+            })
+            .collect()
+    }
+
+    /// Adds the `(trigger, value)` pairs. Nothing is added unless every pair
+    /// has a trigger and a value.
+    pub fn extend<I>(&mut self, pairs: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        for (trigger, value) in Self::normalize(pairs)? {
+            let values = self.values.entry(trigger).or_default();
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes `value` from `trigger`, or every value if `value` is `None`
+    pub fn remove(&mut self, trigger: &str, value: Option<&str>) {
+        let trigger = trigger.to_ascii_lowercase();
+        if let Some(values) = self.values.get_mut(&trigger) {
+            values.retain(|v| value.is_some_and(|value| v != value));
+            if values.is_empty() {
+                self.values.remove(&trigger);
+            }
+        }
+    }
+
+    pub fn get(&self, trigger: &str) -> &[String] {
+        self.values
+            .get(&trigger.to_ascii_lowercase())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// All `(trigger, value)` pairs, ordered by trigger
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().flat_map(|(trigger, values)| {
+            values
+                .iter()
+                .map(move |value| (trigger.as_str(), value.as_str()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(trigger: &str, value: &str) -> (String, String) {
+        (trigger.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn it_adds_and_removes_snippets() -> Result<()> {
+        let mut snippets = Snippets::from_pairs([
+            pair("Addr", "台北市"),
+            pair("addr", "高雄市"),
+            pair("addr", "台北市"),
+        ])?;
+        assert_eq!(snippets.get("ADDR"), ["台北市", "高雄市"]);
+
+        snippets.remove("addr", Some("台北市"));
+        assert_eq!(snippets.get("addr"), ["高雄市"]);
+        snippets.remove("addr", None);
+        assert!(snippets.get("addr").is_empty());
+        assert_eq!(snippets.iter().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn it_rejects_incomplete_snippets() {
+        let mut snippets = Snippets::default();
+        let res = snippets.extend([pair("sig", "A-bêng"), pair("x", "")]);
+        assert!(res.is_err());
+        assert!(snippets.get("sig").is_empty());
+    }
+}
@@ -1,39 +1,127 @@
+use std::borrow::Borrow;
 use std::collections::HashSet;
 
 use anyhow::Result;
 use qp_trie::wrapper::BString;
 use qp_trie::Trie as QpTrie;
 
+use crate::data::segmenter::word_cost;
+use crate::data::snapshot::SnapshotSection;
 use crate::db::models::KeySequence;
 
-type WordTrie = QpTrie<BString, Vec<i64>>;
+type WordTrie = QpTrie<BString, WordEntry>;
 
-pub(crate) struct Trie {
+/// Everything we know about a key sequence. Keeping the segmentation cost
+/// here means each key is stored only once, in the trie.
+pub(crate) struct WordEntry {
+    cost: f64,
+    ids: Vec<i64>,
+}
+
+pub(crate) enum Trie {
+    Built {
+        qp_trie: WordTrie,
+        max_word_length: usize,
+    },
+    /// One section per input type, searched together
+    Snapshot(Vec<SnapshotSection>),
+}
+
+/// Builds a `Trie` from key sequences given in descending frequency.
+pub(crate) struct TrieBuilder {
     qp_trie: WordTrie,
+    max_word_length: usize,
+}
+
+impl TrieBuilder {
+    pub fn new() -> Self {
+        Self {
+            qp_trie: QpTrie::new(),
+            max_word_length: 0,
+        }
+    }
+
+    pub fn push(&mut self, word: &KeySequence) {
+        if let Some(entry) = self.qp_trie.get_mut_str(&word.keys) {
+            // The first (most frequent) occurrence sets the cost
+            entry.ids.push(word.input_id);
+        } else {
+            let word_len = word.keys.chars().count();
+            self.max_word_length =
+                std::cmp::max(self.max_word_length, word_len);
+            let entry = WordEntry {
+                cost: word_cost(word_len, word.n_syls, word.p),
+                ids: vec![word.input_id],
+            };
+            self.qp_trie.insert_str(&word.keys, entry);
+        }
+    }
+
+    pub fn build(self) -> Trie {
+        Trie::Built {
+            qp_trie: self.qp_trie,
+            max_word_length: self.max_word_length,
+        }
+    }
 }
 
 impl Trie {
     pub fn new(inputs: &Vec<KeySequence>) -> Result<Self> {
-        let mut qp_trie: WordTrie = QpTrie::new();
+        let mut builder = TrieBuilder::new();
 
         for word in inputs.iter() {
-            if let Some(ids) = qp_trie.get_mut_str(&word.keys) {
-                ids.push(word.input_id);
-            } else {
-                let v = vec![word.input_id];
-                qp_trie.insert_str(&word.keys, v);
-            }
+            builder.push(word);
         }
 
-        Ok(Self { qp_trie })
+        Ok(builder.build())
+    }
+
+    pub fn from_snapshot(sections: Vec<SnapshotSection>) -> Self {
+        Trie::Snapshot(sections)
+    }
+
+    pub fn max_word_length(&self) -> usize {
+        match self {
+            Trie::Built {
+                max_word_length, ..
+            } => *max_word_length,
+            Trie::Snapshot(sections) => sections
+                .iter()
+                .map(|section| section.max_word_length())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// The segmentation cost of `query`, if it is a word
+    pub fn cost(&self, query: &str) -> Option<f64> {
+        match self {
+            Trie::Built { qp_trie, .. } => {
+                qp_trie.get_str(query).map(|entry| entry.cost)
+            },
+            // Keys found in several sections come from the same rows, so
+            // the first cost found is the same in each
+            Trie::Snapshot(sections) => {
+                sections.iter().find_map(|section| section.get_cost(query))
+            },
+        }
     }
 
     pub fn find_words_by_prefix(&self, query: &str) -> Vec<i64> {
         let mut result = HashSet::new();
-        for (_, vec) in self.qp_trie.iter_prefix_str(query) {
-            for v in vec {
-                result.insert(v.clone());
-            }
+        match self {
+            Trie::Built { qp_trie, .. } => {
+                for (_, entry) in qp_trie.iter_prefix_str(query) {
+                    for v in entry.ids.iter() {
+                        result.insert(*v);
+                    }
+                }
+            },
+            Trie::Snapshot(sections) => {
+                for section in sections.iter() {
+                    result.extend(section.ids_with_prefix(query));
+                }
+            },
         }
         let mut v: Vec<i64> = result.iter().map(|&ea| ea).collect();
         v.sort_unstable();
@@ -41,18 +129,125 @@ impl Trie {
     }
 
     pub fn find_words_from_start<'a>(&self, query: &'a str) -> Vec<&'a str> {
-        self.qp_trie.get_keys_str(query)
+        match self {
+            Trie::Built { qp_trie, .. } => qp_trie.get_keys_str(query),
+            Trie::Snapshot(sections) => sections.get_keys_str(query),
+        }
     }
 
     pub fn contains(&self, query: &str) -> bool {
-        self.qp_trie.contains_key_str(query)
+        match self {
+            Trie::Built { qp_trie, .. } => qp_trie.contains_key_str(query),
+            Trie::Snapshot(sections) => {
+                sections.iter().any(|section| section.contains(query))
+            },
+        }
+    }
+
+    /// Words within `max_edits` insertions, deletions or substitutions of a
+    /// prefix of `query`, as `(prefix length, word)` pairs. The first letter
+    /// is taken to be correct, which keeps the search to a single branch.
+    pub fn find_words_near(
+        &self,
+        query: &str,
+        max_edits: usize,
+    ) -> Vec<(usize, String)> {
+        let first = match query.chars().next() {
+            Some(ch) => &query[..ch.len_utf8()],
+            None => return Vec::new(),
+        };
+
+        let mut result = Vec::new();
+        let mut check = |word: &str| {
+            if word.len() > query.len() + max_edits {
+                return;
+            }
+            let distances = prefix_distances(word, query, max_edits);
+            for (len, &d) in distances.iter().enumerate() {
+                if d <= max_edits && query.is_char_boundary(len) {
+                    result.push((len, word.to_string()));
+                }
+            }
+        };
+
+        match self {
+            Trie::Built { qp_trie, .. } => {
+                for (key, _) in qp_trie.iter_prefix_str(first) {
+                    let bytes: &[u8] = key.borrow();
+                    if let Ok(word) = std::str::from_utf8(bytes) {
+                        check(word);
+                    }
+                }
+            },
+            Trie::Snapshot(sections) => {
+                let mut seen = HashSet::new();
+                for section in sections.iter() {
+                    for word in section.keys_with_prefix(first) {
+                        if seen.insert(word) {
+                            check(word);
+                        }
+                    }
+                }
+            },
+        }
+
+        result
+    }
+}
+
+/// Edit distance between `word` and each prefix of `query`, indexed by the
+/// prefix length in bytes. Distances over `max_edits` are not exact.
+pub(crate) fn prefix_distances(
+    word: &str,
+    query: &str,
+    max_edits: usize,
+) -> Vec<usize> {
+    let query = query.as_bytes();
+    let mut row: Vec<usize> = (0..=query.len()).collect();
+
+    for (i, &w) in word.as_bytes().iter().enumerate() {
+        let mut next = Vec::with_capacity(row.len());
+        next.push(i + 1);
+        for (j, &q) in query.iter().enumerate() {
+            let substitution = row[j] + (w != q) as usize;
+            let insertion = next[j] + 1;
+            let deletion = row[j + 1] + 1;
+            next.push(substitution.min(insertion).min(deletion));
+        }
+        row = next;
+
+        if row.iter().all(|&d| d > max_edits) {
+            break;
+        }
     }
+
+    row
 }
 
 trait Walker<'a> {
     fn get_keys_str(&self, query: &'a str) -> Vec<&'a str>;
 }
 
+impl<'a> Walker<'a> for Vec<SnapshotSection> {
+    fn get_keys_str(&self, query: &'a str) -> Vec<&'a str> {
+        let mut found: Vec<&str> = Vec::new();
+
+        for (i, _) in query.char_indices() {
+            let key = &query[0..(i + 1)];
+
+            if !self.iter().any(|section| section.has_prefix(key)) {
+                break;
+            }
+
+            if self.iter().any(|section| section.contains(key)) {
+                found.push(key);
+            }
+        }
+
+        found
+    }
+}
+
 impl<'a> Walker<'a> for WordTrie {
     fn get_keys_str(&self, query: &'a str) -> Vec<&'a str> {
         let mut found: Vec<&str> = Vec::new();
@@ -95,10 +290,22 @@ mod tests {
         Trie::new(&ks).unwrap()
     }
 
+    #[test]
+    fn it_finds_words_near_a_prefix() {
+        let t = get_trie(vec!["chiah", "chia", "chhiah", "kiah"]);
+        let mut near = t.find_words_near("chiaxyz", 1);
+        near.sort();
+        assert!(near.contains(&(3, "chia".to_string())));
+        assert!(near.contains(&(4, "chia".to_string())));
+        assert!(near.contains(&(4, "chiah".to_string())));
+        assert!(near.contains(&(5, "chiah".to_string())));
+        assert!(near.iter().all(|(_, w)| w != "kiah"));
+    }
+
     #[test]
     fn it_gets_contained_keys() {
         let t = get_trie(vec!["ball", "tomato", "balloon", "balloonanimal"]);
-        let res = t.qp_trie.get_keys_str("balloonanimal");
+        let res = t.find_words_from_start("balloonanimal");
         assert_eq!(res.len(), 3);
         assert_eq!(res[0], "ball");
         assert_eq!(res[1], "balloon");
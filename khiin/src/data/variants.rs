@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::db::Database;
+
+/// Commonly used forms of standard Hanji, such as ê for 的
+#[derive(Default)]
+pub(crate) struct HanjiVariants {
+    forms: HashMap<String, String>,
+    max_len: usize,
+}
+
+impl HanjiVariants {
+    pub fn new(db: &Database) -> Result<Self> {
+        Ok(Self::from_pairs(db.select_hanji_variants()?))
+    }
+
+    pub fn from_pairs<I>(pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut forms = HashMap::new();
+        let mut max_len = 0;
+
+        for (standard, variant) in pairs {
+            max_len = max_len.max(standard.chars().count());
+            forms.insert(standard, variant);
+        }
+
+        Self { forms, max_len }
+    }
+
+    /// Replaces each standard form in `text` with its variant, longest
+    /// match first
+    pub fn rewrite(&self, text: &str) -> String {
+        if self.forms.is_empty() {
+            return text.to_string();
+        }
+
+        let bounds: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        let n_chars = bounds.len() - 1;
+        let mut result = String::new();
+        let mut i = 0;
+
+        while i < n_chars {
+            let longest = self.max_len.min(n_chars - i);
+            let found = (1..=longest).rev().find_map(|len| {
+                let standard = &text[bounds[i]..bounds[i + len]];
+                self.forms.get(standard).map(|variant| (len, variant))
+            });
+
+            match found {
+                Some((len, variant)) => {
+                    result.push_str(variant);
+                    i += len;
+                },
+                None => {
+                    result.push_str(&text[bounds[i]..bounds[i + 1]]);
+                    i += 1;
+                },
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_rewrites_longest_match_first() {
+        let variants = HanjiVariants::from_pairs([
+            ("的".to_string(), "ê".to_string()),
+            ("佇".to_string(), "ti̍t".to_string()),
+            ("佇遮".to_string(), "ti̍t-chia".to_string()),
+        ]);
+        assert_eq!(variants.rewrite("我的"), "我ê");
+        assert_eq!(variants.rewrite("佇遮"), "ti̍t-chia");
+        assert_eq!(variants.rewrite("佇厝"), "ti̍t厝");
+        assert_eq!(variants.rewrite("hó"), "hó");
+    }
+}
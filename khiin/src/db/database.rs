@@ -4,9 +4,11 @@ use std::ops::DerefMut;
 use std::path::Path;
 use std::path::PathBuf;
 
+use anyhow::anyhow;
 use anyhow::Result;
 use once_cell::sync::Lazy;
 use rusqlite::backup::Progress;
+use rusqlite::ffi;
 use rusqlite::named_params;
 use rusqlite::params_from_iter;
 use rusqlite::Connection;
@@ -21,7 +23,13 @@ use super::models::KeyConversion;
 use super::models::KeySequence;
 
 static MIGRATIONS: Lazy<Migrations> = Lazy::new(|| {
-    Migrations::new(vec![M::up(include_str!("migrations/001/up.sql"))])
+    Migrations::new(vec![
+        M::up(include_str!("migrations/001/up.sql")),
+        M::up(include_str!("migrations/002/up.sql")),
+        M::up(include_str!("migrations/003/up.sql")),
+        M::up(include_str!("migrations/004/up.sql")),
+        M::up(include_str!("migrations/005/up.sql")),
+    ])
 });
 
 type Noop = Box<dyn Fn(Progress)>;
@@ -61,6 +69,19 @@ impl Database {
         }
     }
 
+    /// Loads a database image (the contents of a `khiin.db` file) without
+    /// going through the filesystem, e.g. for the WebAssembly build.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        let mut this = Self {
+            conn,
+            file: PathBuf::new(),
+        };
+        this.deserialize(bytes)?;
+        this.migrate_to_latest()?;
+        Ok(this)
+    }
+
     fn init(mut self) -> Result<Self> {
         self.set_pragmas()?;
         self.migrate_to_latest()?;
@@ -98,43 +119,89 @@ impl Database {
         Ok(())
     }
 
+    fn deserialize(&mut self, bytes: &[u8]) -> Result<()> {
+        let len = bytes.len();
+        let flags = ffi::SQLITE_DESERIALIZE_FREEONCLOSE
+            | ffi::SQLITE_DESERIALIZE_RESIZEABLE;
+
+        // SQLite takes ownership of the buffer (FREEONCLOSE), so it must be
+        // allocated by SQLite rather than by Rust.
+        let rc = unsafe {
+            let buf = ffi::sqlite3_malloc64(len as u64) as *mut u8;
+            if buf.is_null() {
+                return Err(anyhow!("Unable to allocate database image"));
+            }
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, len);
+            ffi::sqlite3_deserialize(
+                self.conn.handle(),
+                c"main".as_ptr(),
+                buf,
+                len as i64,
+                len as i64,
+                flags as u32,
+            )
+        };
+
+        if rc != ffi::SQLITE_OK {
+            return Err(anyhow!("Unable to load database image ({})", rc));
+        }
+
+        Ok(())
+    }
+
     pub fn select_all_words_by_freq(
         &self,
-        input_type: InputType,
+        input_types: &[InputType],
     ) -> Result<Vec<KeySequence>> {
-        let sql = include_str!("sql/select_all_words_by_freq.sql");
-
         let mut result = Vec::new();
+        self.for_each_word_by_freq(input_types, |word| result.push(word))?;
+        Ok(result)
+    }
+
+    /// Like `select_all_words_by_freq`, but hands each row to `f` instead of
+    /// collecting them, to keep peak memory low while building the dictionary.
+    pub fn for_each_word_by_freq<F>(
+        &self,
+        input_types: &[InputType],
+        mut f: F,
+    ) -> Result<()>
+    where
+        F: FnMut(KeySequence),
+    {
+        let sql = format!(
+            include_str!("sql/select_all_words_by_freq.sql"),
+            input_types = input_type_list(input_types),
+        );
+
         let mut stmt = self.conn.prepare(&sql)?;
-        let mut rows = stmt.query([input_type as i64])?;
+        let mut rows = stmt.query([])?;
 
         while let Some(row) = rows.next()? {
-            result.push(row.try_into()?);
+            f(row.try_into()?);
         }
 
-        Ok(result)
+        Ok(())
     }
 
     pub fn select_conversions(
         &self,
-        input_type: InputType,
+        input_types: &[InputType],
         query: &str,
         limit: Option<usize>,
     ) -> Result<Vec<KeyConversion>> {
-        let sql = match limit {
-            Some(n) => format!(
-                include_str!("sql/select_conversions.sql"),
-                limit = format!("limit {}", n)
-            ),
-            None => {
-                format!(include_str!("sql/select_conversions.sql"), limit = "")
-            },
+        let limit = match limit {
+            Some(n) => format!("limit {}", n),
+            None => String::new(),
         };
+        let sql = format!(
+            include_str!("sql/select_conversions.sql"),
+            input_types = input_type_list(input_types),
+            limit = limit,
+        );
 
         let mut stmt = self.conn.prepare(&sql)?;
         let mut rows = stmt.query(named_params! {
             ":query": query,
-            ":input_type": input_type as i64,
         })?;
 
         let mut result = Vec::new();
@@ -147,15 +214,21 @@ impl Database {
 
     pub fn select_conversions_by_hanlo(
         &self,
-        input_type: InputType,
+        input_types: &[InputType],
         query: &str,
         is_hanji_first: bool,
         is_khinless: bool,
+        limit: Option<usize>,
     ) -> Result<Vec<KeyConversion>> {
+        let limit = match limit {
+            Some(n) => format!("limit {}", n),
+            None => String::new(),
+        };
         let sql = if is_hanji_first {
             format!(
                 include_str!("sql/select_conversions_by_hanji.sql"),
-                limit = "limit 1",
+                input_types = input_type_list(input_types),
+                limit = limit,
                 khin_mode = if is_khinless {
                     "khinless_ok"
                 } else {
@@ -165,7 +238,8 @@ impl Database {
         } else {
             format!(
                 include_str!("sql/select_conversions_by_lomaji.sql"),
-                limit = "limit 1",
+                input_types = input_type_list(input_types),
+                limit = limit,
                 khin_mode = if is_khinless {
                     "khinless_ok"
                 } else {
@@ -177,7 +251,6 @@ impl Database {
         let mut stmt = self.conn.prepare(&sql)?;
         let mut rows = stmt.query(named_params! {
             ":query": query,
-            ":input_type": input_type as i64,
         })?;
 
         let mut result = Vec::new();
@@ -276,15 +349,204 @@ impl Database {
         Ok(result)
     }
 
+    /// Conversions for words that complete `query`, with the user's most used
+    /// words first and then the most frequent
+    pub fn select_predictions(
+        &self,
+        input_types: &[InputType],
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<KeyConversion>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let sql = format!(
+            include_str!("sql/select_predictions.sql"),
+            input_types = input_type_list(input_types),
+            limit = limit,
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(named_params! {
+            ":query": query,
+            ":query_end": prefix_end(query),
+        })?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(row.try_into()?);
+        }
+
+        Ok(result)
+    }
+
+    /// Words most often committed after `lgram`
+    pub fn select_next_words(
+        &self,
+        lgram: &str,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        let sql =
+            format!(include_str!("sql/select_next_words.sql"), limit = limit);
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(named_params! {
+            ":lgram": lgram,
+        })?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(row.get("rgram")?);
+        }
+
+        Ok(result)
+    }
+
+    /// Pairs of standard Hanji and their commonly used variant forms
+    pub fn select_hanji_variants(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare(include_str!("sql/select_hanji_variants.sql"))?;
+        let mut rows = stmt.query([])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push((row.get("standard")?, row.get("variant")?));
+        }
+
+        Ok(result)
+    }
+
+    /// The lomaji readings of a dictionary word, most frequent first
+    pub fn select_readings(&self, output: &str) -> Result<Vec<String>> {
+        let mut stmt =
+            self.conn.prepare(include_str!("sql/select_readings.sql"))?;
+        let mut rows = stmt.query(named_params! { ":output": output })?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(row.get("input")?);
+        }
+
+        Ok(result)
+    }
+
+    /// Every lomaji spelling in the dictionary, most frequent first
+    pub fn select_lomaji_words(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(include_str!("sql/select_lomaji_words.sql"))?;
+        let mut rows = stmt.query([])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(row.get("output")?);
+        }
+
+        Ok(result)
+    }
+
+    /// Counts one more commit of `rgram` right after `lgram`
+    pub fn record_bigram(&self, lgram: &str, rgram: &str) -> Result<()> {
+        self.conn.execute(
+            include_str!("sql/upsert_bigram.sql"),
+            named_params! {
+                ":lgram": lgram,
+                ":rgram": rgram,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Every user snippet as (trigger, value), grouped by trigger
+    pub fn select_snippets(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt =
+            self.conn.prepare(include_str!("sql/select_snippets.sql"))?;
+        let mut rows = stmt.query([])?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push((row.get("trigger")?, row.get("value")?));
+        }
+
+        Ok(result)
+    }
+
+    /// The text that `trigger` expands into, in the order it was added
+    pub fn select_snippets_by_trigger(
+        &self,
+        trigger: &str,
+    ) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(include_str!("sql/select_snippets_by_trigger.sql"))?;
+        let mut rows = stmt.query(named_params! { ":trigger": trigger })?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(row.get("value")?);
+        }
+
+        Ok(result)
+    }
+
+    /// Adds the (trigger, value) pairs, all or none
+    pub fn insert_snippets(
+        &mut self,
+        pairs: &[(String, String)],
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        insert_snippets(&tx, pairs)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes the snippets matching `trigger` and `value`. `None` matches
+    /// anything, so `delete_snippets(None, None)` deletes every snippet.
+    pub fn delete_snippets(
+        &self,
+        trigger: Option<&str>,
+        value: Option<&str>,
+    ) -> Result<()> {
+        self.conn.execute(
+            include_str!("sql/delete_snippets.sql"),
+            named_params! {
+                ":trigger": trigger,
+                ":value": value,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Replaces every snippet with the (trigger, value) pairs. The old
+    /// snippets are kept if any pair fails to insert.
+    pub fn replace_snippets(
+        &mut self,
+        pairs: &[(String, String)],
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            include_str!("sql/delete_snippets.sql"),
+            named_params! {
+                ":trigger": None::<&str>,
+                ":value": None::<&str>,
+            },
+        )?;
+        insert_snippets(&tx, pairs)?;
+        tx.commit()?;
+        Ok(())
+    }
+
     pub fn select_conversions_for_multiple(
         &self,
-        input_type: InputType,
+        input_types: &[InputType],
         words: &Vec<&str>,
     ) -> Result<Vec<KeyConversion>> {
         let sql = format!(
             include_str!("sql/select_conversions_for_multiple.sql"),
             vars = repeat_vars(words.len()),
-            input_type = input_type as i64,
+            input_types = input_type_list(input_types),
         );
 
         log::trace!("{}", sql);
@@ -328,10 +590,54 @@ impl TryFrom<&Row<'_>> for KeyConversion {
             khin_ok: row.get("khin_ok")?,
             khinless_ok: row.get("khinless_ok")?,
             annotation: row.get("annotation")?,
+            gloss: row.get("gloss")?,
+            variant: row.get("variant")?,
+            fuzzy: false,
+            predicted: false,
         })
     }
 }
 
+fn insert_snippets(
+    conn: &Connection,
+    pairs: &[(String, String)],
+) -> Result<()> {
+    let mut stmt = conn.prepare(include_str!("sql/insert_snippet.sql"))?;
+    for (trigger, value) in pairs {
+        stmt.execute(named_params! {
+            ":trigger": trigger,
+            ":value": value,
+        })?;
+    }
+    Ok(())
+}
+
+/// SQL list of `input_types`. Toneless key sequences match in every mode,
+/// so they are always included.
+fn input_type_list(input_types: &[InputType]) -> String {
+    let mut types = vec![InputType::Detoned as i64];
+    types.extend(input_types.iter().map(|&ty| ty as i64));
+    types
+        .iter()
+        .map(|ty| ty.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A bound just above every string that starts with `prefix`, so that prefix
+/// searches can use an index
+fn prefix_end(prefix: &str) -> String {
+    let mut end = prefix.to_string();
+    while let Some(ch) = end.pop() {
+        if let Some(next) = char::from_u32(ch as u32 + 1) {
+            end.push(next);
+            return end;
+        }
+    }
+    // Every string starts with the empty prefix
+    char::MAX.to_string()
+}
+
 // from rusqlite docs
 fn repeat_vars(count: usize) -> String {
     assert_ne!(count, 0);
@@ -395,10 +701,19 @@ mod tests {
         assert!(db.is_ok());
     }
 
+    #[test]
+    fn it_loads_the_db_from_bytes() {
+        let bytes = std::fs::read(debug_db_path()).unwrap();
+        let db = Database::from_bytes(&bytes).expect("Could not load DB");
+        let res = db.select_conversions(&[InputType::Numeric], "ho2", None);
+        assert!(res.unwrap().iter().any(|row| row.output == "好"));
+        assert!(Database::from_bytes(b"not a database").is_err());
+    }
+
     #[test_log::test]
     fn it_loads_results() {
         let db = Database::new(&debug_db_path()).expect("Could not load DB");
-        let res = db.select_all_words_by_freq(InputType::Numeric);
+        let res = db.select_all_words_by_freq(&[InputType::Numeric]);
         assert!(res.is_ok());
         let res = res.unwrap();
         assert!(res.len() > 100);
@@ -414,7 +729,7 @@ mod tests {
     fn it_finds_conversions() {
         let db = get_db();
         let res = db
-            .select_conversions(InputType::Numeric, "ho2", None)
+            .select_conversions(&[InputType::Numeric], "ho2", None)
             .unwrap();
         assert!(res.len() >= 2);
         assert!(res.iter().any(|row| row.output == "好"));
@@ -422,13 +737,119 @@ mod tests {
         assert!(res[0].annotation.is_none());
     }
 
+    #[test]
+    fn it_loads_glosses_and_variants() {
+        let db = get_db();
+        let res = db
+            .select_conversions(&[InputType::Numeric], "bo5", None)
+            .unwrap();
+        let find = |output: &str| res.iter().find(|row| row.output == output);
+        assert_eq!(find("無").unwrap().gloss.as_deref(), Some("not have; no"));
+        assert!(find("无").unwrap().variant);
+
+        let variants = db.select_hanji_variants().unwrap();
+        assert!(variants.contains(&("个".to_string(), "的".to_string())));
+    }
+
+    #[test]
+    fn it_merges_input_types() {
+        let db = get_db();
+        let types = [InputType::Telex, InputType::Numeric];
+        for query in ["hos", "ho2"] {
+            let res = db.select_conversions(&types, query, None).unwrap();
+            assert!(res.iter().any(|row| row.output == "好"));
+        }
+        let res = db.select_conversions(&[InputType::Telex], "ho2", None);
+        assert!(res.unwrap().is_empty());
+    }
+
     #[test_log::test]
     fn it_converts_by_id_vec() {
         let db = get_db();
         let words = vec!["ho", "hong"];
         let res = db
-            .select_conversions_for_multiple(InputType::Numeric, &words)
+            .select_conversions_for_multiple(&[InputType::Numeric], &words)
             .unwrap();
         assert!(res.len() >= 20);
     }
+
+    #[test]
+    fn it_selects_predictions() {
+        let db = get_db();
+        let res = db
+            .select_predictions(&[InputType::Numeric], "chia", 5)
+            .unwrap();
+        assert_eq!(res.len(), 5);
+        assert!(res.iter().all(|row| {
+            row.key_sequence.starts_with("chia") && row.key_sequence != "chia"
+        }));
+    }
+
+    #[test]
+    fn it_stores_snippets() {
+        let mut db = get_db();
+        let pair = |t: &str, v: &str| (t.to_string(), v.to_string());
+        db.insert_snippets(&[
+            pair("addr", "台北市"),
+            pair("addr", "台南市"),
+            pair("addr", "台北市"),
+            pair("sig", "Lí hó"),
+        ])
+        .unwrap();
+        let res = db.select_snippets_by_trigger("addr").unwrap();
+        assert_eq!(res, vec!["台北市", "台南市"]);
+
+        db.delete_snippets(Some("addr"), Some("台北市")).unwrap();
+        let res = db.select_snippets_by_trigger("addr").unwrap();
+        assert_eq!(res, vec!["台南市"]);
+        assert_eq!(db.select_snippets().unwrap().len(), 2);
+
+        db.replace_snippets(&[pair("tel", "02-1234")]).unwrap();
+        assert_eq!(db.select_snippets().unwrap(), vec![pair("tel", "02-1234")]);
+
+        db.delete_snippets(None, None).unwrap();
+        assert!(db.select_snippets().unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_searches_predictions_by_index() {
+        let db = get_db();
+        let sql = format!(
+            include_str!("sql/select_predictions.sql"),
+            input_types = input_type_list(&[InputType::Numeric]),
+            limit = 5,
+        );
+        let mut stmt = db
+            .conn
+            .prepare(&format!("explain query plan {}", sql))
+            .unwrap();
+        let plan = stmt
+            .query_map(
+                named_params! { ":query": "chia", ":query_end": "chib" },
+                |row| row.get::<_, String>(3),
+            )
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(plan
+            .iter()
+            .any(|step| step.contains("key_sequences_key_sequence_index")));
+    }
+
+    #[test]
+    fn it_finds_the_end_of_a_prefix() {
+        assert_eq!(prefix_end("chia"), "chib");
+        assert_eq!(prefix_end("a-"), "a.");
+        assert!(prefix_end("").as_str() > "zzz");
+    }
+
+    #[test]
+    fn it_records_bigrams() {
+        let db = get_db();
+        db.record_bigram("食", "飯").unwrap();
+        db.record_bigram("食", "飯").unwrap();
+        db.record_bigram("食", "茶").unwrap();
+        let res = db.select_next_words("食", 5).unwrap();
+        assert_eq!(res, vec!["飯", "茶"]);
+    }
 }
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::hash::Hash;
 
@@ -15,6 +16,8 @@ use crate::db::models::InputLookup;
 pub struct CsvFiles<'a> {
     pub input_csv: Cow<'a, str>,
     pub conversion_csv: Cow<'a, str>,
+    pub gloss_csv: Option<Cow<'a, str>>,
+    pub variant_csv: Option<Cow<'a, str>>,
 }
 
 impl<'a> CsvFiles<'a> {
@@ -22,8 +25,22 @@ impl<'a> CsvFiles<'a> {
         Self {
             input_csv: csv_data,
             conversion_csv: conversion_data,
+            gloss_csv: None,
+            variant_csv: None,
         }
     }
+
+    /// Adds glosses and variant markers to the conversions
+    pub fn with_glosses(mut self, gloss_data: Cow<'a, str>) -> Self {
+        self.gloss_csv = Some(gloss_data);
+        self
+    }
+
+    /// Adds a table of commonly used variants of standard Hanji
+    pub fn with_variants(mut self, variant_data: Cow<'a, str>) -> Self {
+        self.variant_csv = Some(variant_data);
+        self
+    }
 }
 
 #[derive(Debug, Deserialize, Eq, PartialEq, Hash)]
@@ -47,6 +64,21 @@ pub struct CsvConversion {
     pub khinless_ok: bool,
 }
 
+#[derive(Debug, Deserialize, Eq, PartialEq, Hash)]
+pub struct CsvGloss {
+    pub input: String,
+    pub output: String,
+    pub gloss: Option<String>,
+    #[serde(default)]
+    pub variant: bool,
+}
+
+#[derive(Debug, Deserialize, Eq, PartialEq, Hash)]
+pub struct CsvVariant {
+    pub standard: String,
+    pub variant: String,
+}
+
 fn load_freq_records(csv_data: Cow<str>) -> Result<(Vec<CsvFrequency>, i64)> {
     let reader = Reader::from_reader(csv_data.as_bytes());
     let mut total_count = 0;
@@ -134,6 +166,8 @@ pub fn conversions_from_csv(
                     khin_ok,
                     khinless_ok,
                     is_hanji: is_hanji,
+                    gloss: None,
+                    variant: false,
                 });
             } else {
                 log::debug!(
@@ -151,3 +185,58 @@ pub fn conversions_from_csv(
 
     Ok(records)
 }
+
+/// Fills in the gloss and variant columns from a CSV with the headers
+/// `input,output,gloss,variant`. Rows without a matching conversion are
+/// skipped.
+pub fn glosses_from_csv(
+    csv_data: Cow<str>,
+    input_lookup: &InputLookup,
+    conversions: &mut [Conversion],
+) -> Result<()> {
+    let mut glosses = HashMap::new();
+    let reader = Reader::from_reader(csv_data.as_bytes());
+
+    for result in reader.into_deserialize() {
+        let CsvGloss {
+            input,
+            output,
+            gloss,
+            variant,
+        } = result?;
+
+        if let Some(input_id) = input_lookup.id_of(&input) {
+            glosses.insert((input_id, output), (gloss, variant));
+        }
+    }
+
+    let mut n = 0;
+
+    for conv in conversions.iter_mut() {
+        if let Some((gloss, variant)) =
+            glosses.remove(&(conv.input_id, conv.output.clone()))
+        {
+            conv.gloss = gloss.filter(|g| !g.is_empty());
+            conv.variant = variant;
+            n += 1;
+        }
+    }
+
+    log::debug!("Total glosses: {}", n);
+
+    Ok(())
+}
+
+/// Reads a CSV with the headers `standard,variant`
+pub fn variants_from_csv(csv_data: Cow<str>) -> Result<Vec<CsvVariant>> {
+    let reader = Reader::from_reader(csv_data.as_bytes());
+    let mut records = vec![];
+
+    for result in reader.into_deserialize() {
+        records.push(result?);
+    }
+
+    log::debug!("Total Hanji variants: {}", records.len());
+
+    Ok(records)
+}
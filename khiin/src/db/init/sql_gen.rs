@@ -11,7 +11,10 @@ use crate::db::models::KeySequence;
 
 use super::csv::conversions_from_csv;
 use super::csv::frequencies_from_csv;
+use super::csv::glosses_from_csv;
+use super::csv::variants_from_csv;
 use super::csv::CsvFiles;
+use super::csv::CsvVariant;
 
 pub(crate) fn collect_data(
     csv_files: CsvFiles,
@@ -19,10 +22,16 @@ pub(crate) fn collect_data(
     let CsvFiles {
         input_csv,
         conversion_csv,
+        gloss_csv,
+        ..
     } = csv_files;
 
     let (inputs, input_lookup) = frequencies_from_csv(input_csv)?;
-    let conversions = conversions_from_csv(conversion_csv, &input_lookup)?;
+    let mut conversions = conversions_from_csv(conversion_csv, &input_lookup)?;
+
+    if let Some(gloss_csv) = gloss_csv {
+        glosses_from_csv(gloss_csv, &input_lookup, &mut conversions)?;
+    }
     let key_sequences = generate_key_sequences(&inputs)?;
 
     log::debug!("Total key sequences: {}", key_sequences.len());
@@ -68,6 +77,8 @@ pub(crate) fn insert_conversions(
             row.khin_ok,
             row.khinless_ok,
             row.is_hanji as i64,
+            row.gloss,
+            row.variant as i64,
         ])?;
     }
 
@@ -77,6 +88,24 @@ pub(crate) fn insert_conversions(
     Ok(())
 }
 
+pub(crate) fn insert_hanji_variants(
+    conn: &mut Connection,
+    variants: Vec<CsvVariant>,
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    let mut stmt =
+        tx.prepare(include_str!("../sql/insert_hanji_variants.sql"))?;
+
+    for row in variants {
+        stmt.execute(params![row.standard, row.variant])?;
+    }
+
+    drop(stmt);
+    tx.commit()?;
+
+    Ok(())
+}
+
 pub(crate) fn insert_key_sequences(
     conn: &mut Connection,
     rows: Vec<KeySequence>,
@@ -112,8 +141,12 @@ pub(crate) fn build_sql(conn: &mut Connection) -> Result<()> {
 
 pub(crate) fn build_sql_from_csv(
     conn: &mut Connection,
-    csv_files: CsvFiles,
+    mut csv_files: CsvFiles,
 ) -> Result<()> {
+    if let Some(variant_csv) = csv_files.variant_csv.take() {
+        insert_hanji_variants(conn, variants_from_csv(variant_csv)?)?;
+    }
+
     let (inputs, mut conversions, mut key_sequences) = collect_data(csv_files)?;
 
     conversions.sort_by(|a, b| a.input_id.cmp(&b.input_id));
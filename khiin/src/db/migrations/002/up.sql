@@ -0,0 +1,5 @@
+-- Lets prefix searches on key sequences use a range scan
+create index key_sequences_key_sequence_index on key_sequences (
+    "key_sequence",
+    "input_type"
+);
@@ -0,0 +1,40 @@
+alter table conversions add column "gloss" text;
+
+alter table conversions add column "variant" integer not null default 0;
+
+drop view if exists conversion_lookups;
+
+create view
+    conversion_lookups (
+        "key_sequence",
+        "input_type",
+        "n_syls",
+        "input",
+        "input_id",
+        "output",
+        "weight",
+        "khin_ok",
+        "khinless_ok",
+        "annotation",
+        "is_hanji",
+        "gloss",
+        "variant"
+    ) as
+select
+    "n"."key_sequence",
+    "n"."input_type",
+    "n"."n_syls",
+    "f"."input",
+    "n"."input_id",
+    "c"."output",
+    "c"."weight",
+    "c"."khin_ok",
+    "c"."khinless_ok",
+    "c"."annotation",
+    "c"."is_hanji",
+    "c"."gloss",
+    "c"."variant"
+from
+    key_sequences as "n"
+    join inputs as "f" on "f"."id" = "n"."input_id"
+    join conversions as "c" on "f"."id" = "c"."input_id";
@@ -0,0 +1,6 @@
+create table
+    hanji_variants (
+        "standard" text not null,
+        "variant" text not null,
+        unique ("standard")
+    );
@@ -0,0 +1,8 @@
+create table
+    snippets (
+        "trigger" text not null,
+        "value" text not null,
+        unique ("trigger", "value")
+    );
+
+create index snippet_trigger_index on snippets ("trigger");
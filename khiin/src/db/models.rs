@@ -11,4 +11,5 @@ pub use key_conversion::CaseType;
 pub use key_sequence::generate_key_sequences;
 pub use key_sequence::InputType;
 pub use key_sequence::KeySequence;
+pub use key_sequence::MIN_ABBREVIATION_LEN;
 pub use lookup::InputLookup;
@@ -7,4 +7,6 @@ pub struct Conversion {
     pub khin_ok: bool,
     pub khinless_ok: bool,
     pub is_hanji: bool,
+    pub gloss: Option<String>,
+    pub variant: bool,
 }
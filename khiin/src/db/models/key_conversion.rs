@@ -14,6 +14,14 @@ pub struct KeyConversion {
     pub khin_ok: bool,
     pub khinless_ok: bool,
     pub annotation: Option<String>,
+    /// Short English or Mandarin meaning
+    pub gloss: Option<String>,
+    /// A variant or non-standard character
+    pub variant: bool,
+    /// Found by correcting a typing mistake
+    pub fuzzy: bool,
+    /// Completes the input typed so far
+    pub predicted: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -145,6 +153,10 @@ mod tests {
             khin_ok: true,
             khinless_ok: true,
             annotation: None,
+            gloss: None,
+            variant: false,
+            fuzzy: false,
+            predicted: false,
         }
     }
 
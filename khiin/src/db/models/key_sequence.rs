@@ -10,6 +10,9 @@ use rusqlite::types::ToSqlOutput;
 use rusqlite::types::ValueRef;
 use rusqlite::ToSql;
 
+/// A single letter is usually the start of a syllable, not an abbreviation
+pub const MIN_ABBREVIATION_LEN: usize = 2;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(i64)]
 // TODO
@@ -18,6 +21,8 @@ pub enum InputType {
     Detoned = 0,
     Numeric = 1,
     Telex = 2,
+    /// The first letter of each syllable, e.g. `tg` for `Tâi-gí`
+    Abbreviated = 3,
 }
 
 impl InputType {
@@ -37,6 +42,7 @@ impl FromSql for InputType {
         let ty = match value.as_i64()? {
             1 => InputType::Numeric,
             2 => InputType::Telex,
+            3 => InputType::Abbreviated,
             _ => InputType::Detoned,
         };
         FromSqlResult::from(Ok(ty))
@@ -124,6 +130,16 @@ impl KeySequence {
 
         result
     }
+
+    pub fn of_abbreviation(keys: String, input: &Input) -> Self {
+        Self {
+            keys,
+            input_type: InputType::Abbreviated,
+            n_syls: input.n_syls,
+            input_id: input.id,
+            p: input.p,
+        }
+    }
 }
 
 pub fn generate_key_sequences(inputs: &Vec<Input>) -> Result<Vec<KeySequence>> {
@@ -179,11 +195,21 @@ fn generate_key_sequence(input: &Input) -> Result<Vec<KeySequence>> {
         }
     });
 
+    let abbreviation = detoned_syls
+        .iter()
+        .filter_map(|syl| syl[0].chars().next())
+        .collect::<String>()
+        .to_lowercase();
     let numeric = multi_cartesian_product(numeric_syls);
     let telex = multi_cartesian_product(telex_syls);
     let detoned = multi_cartesian_product(detoned_syls);
 
-    Ok(KeySequence::of_multi_syl_set(numeric, telex, detoned, input))
+    let mut result =
+        KeySequence::of_multi_syl_set(numeric, telex, detoned, input);
+    if abbreviation.len() >= MIN_ABBREVIATION_LEN {
+        result.push(KeySequence::of_abbreviation(abbreviation, input));
+    }
+    Ok(result)
 }
 
 fn multi_cartesian_product(constituents: Vec<Vec<String>>) -> Vec<String> {
@@ -248,7 +274,7 @@ mod tests {
             key_seq(1, "asbo", InputType::Telex, 2),
             key_seq(1, "abo", InputType::Telex, 2),
         ];
-        assert_eq!(result.len(), 9);
+        assert_eq!(result.len(), 10);
         for item in &expect {
             assert!(result.contains(item));
         }
@@ -264,6 +290,9 @@ mod tests {
         for item in &expect {
             assert!(result.contains(item));
         }
+        assert!(!result
+            .iter()
+            .any(|seq| seq.input_type == InputType::Abbreviated));
 
         let result = generate_key_sequence(&input_3);
         assert!(result.is_ok());
@@ -279,8 +308,9 @@ mod tests {
         for item in &expect {
             assert!(result.contains(item));
         }
+        assert!(result.contains(&key_seq(3, "ht", InputType::Abbreviated, 2)));
 
         let result = generate_key_sequences(&vec![input_1, input_2]).unwrap();
-        assert_eq!(result.len(), 12);
+        assert_eq!(result.len(), 13);
     }
 }
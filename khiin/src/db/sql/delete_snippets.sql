@@ -0,0 +1,6 @@
+delete from
+    snippets
+
+where
+    (:trigger is null or "trigger" = :trigger)
+    and (:value is null or "value" = :value)
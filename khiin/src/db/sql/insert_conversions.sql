@@ -6,7 +6,9 @@ insert into
         annotation,
         khin_ok,
         khinless_ok,
-        is_hanji
+        is_hanji,
+        gloss,
+        variant
     )
 values
-    (?, ?, ?, ?, ?, ?, ?)
\ No newline at end of file
+    (?, ?, ?, ?, ?, ?, ?, ?, ?)
\ No newline at end of file
@@ -0,0 +1,4 @@
+insert into
+    hanji_variants (standard, variant)
+values
+    (?, ?)
\ No newline at end of file
@@ -0,0 +1,5 @@
+insert or ignore into
+    snippets ("trigger", "value")
+
+values
+    (:trigger, :value)
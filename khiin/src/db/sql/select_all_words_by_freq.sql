@@ -7,7 +7,6 @@ select
 from
     key_sequences
 where
-    input_type = ? or
-    input_type = 0 -- Toneless
+    input_type in ({input_types})
 order by
     p desc
@@ -13,10 +13,7 @@ from
 
 where
     c.key_sequence = :query
-and (
-    c.input_type = :input_type
-    or c.input_type = 0
-)
+and c.input_type in ({input_types})
 
 order by
     -- b.n desc,
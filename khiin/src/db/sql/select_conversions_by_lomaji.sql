@@ -13,10 +13,7 @@ from
 
 where
     c.key_sequence = :query
-and (
-    c.input_type = :input_type
-    or c.input_type = 0
-) and c.{khin_mode} = 1
+and c.input_type in ({input_types}) and c.{khin_mode} = 1
 
 order by
     c.is_hanji asc,
@@ -11,10 +11,7 @@ with cte as (
         conversion_lookups c
     where
         c.key_sequence in ({vars})
-        and (
-            c.input_type = {input_type}
-            or c.input_type = 0
-        )
+        and c.input_type in ({input_types})
 )
 select *
 from cte
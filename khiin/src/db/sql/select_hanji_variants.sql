@@ -0,0 +1,6 @@
+select
+    standard,
+    variant
+
+from
+    hanji_variants
\ No newline at end of file
@@ -0,0 +1,10 @@
+select
+    output
+from
+    conversions
+where
+    is_hanji = 0
+group by
+    output
+order by
+    max(weight) desc
@@ -0,0 +1,13 @@
+select
+    rgram
+
+from
+    bigrams
+
+where
+    lgram = :lgram
+
+order by
+    n desc
+
+limit {limit}
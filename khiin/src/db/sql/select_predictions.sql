@@ -0,0 +1,36 @@
+with cte as (
+    select
+        c.*,
+        f.corpus_count,
+        row_number() over (
+            partition by c.output
+            order by
+                length(c.key_sequence) asc,
+                c.weight desc
+        ) as rn
+    from
+        conversion_lookups c
+        join inputs f on f.id = c.input_id
+    where
+        c.input_type in ({input_types})
+        and c.key_sequence > :query
+        and c.key_sequence < :query_end
+)
+select
+    cte.*
+
+from
+    cte
+
+    left join unigrams u
+        on cte.output = u.gram
+
+where
+    cte.rn = 1
+
+order by
+    u.n desc,
+    cte.corpus_count desc,
+    cte.weight desc
+
+limit {limit}
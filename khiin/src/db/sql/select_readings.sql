@@ -0,0 +1,15 @@
+select
+    i.input
+
+from
+    conversions c
+    join inputs i on i.id = c.input_id
+
+where
+    c.output = :output
+
+group by
+    i.input
+
+order by
+    max(c.weight) desc
@@ -0,0 +1,10 @@
+select
+    "trigger",
+    "value"
+
+from
+    snippets
+
+order by
+    "trigger",
+    rowid
@@ -0,0 +1,11 @@
+select
+    "value"
+
+from
+    snippets
+
+where
+    "trigger" = :trigger
+
+order by
+    rowid
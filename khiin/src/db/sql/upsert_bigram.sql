@@ -0,0 +1,10 @@
+insert into
+    bigrams (lgram, rgram, n)
+
+values
+    (:lgram, :rgram, 1)
+
+on conflict (lgram, rgram) do
+update
+set
+    n = n + 1
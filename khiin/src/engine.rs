@@ -8,49 +8,167 @@ use anyhow::Result;
 
 use protobuf::Message;
 
+use khiin_ji::ascii::KhinMark;
+use khiin_ji::ascii::Spelling;
+use khiin_ji::contains_hanji;
+use khiin_ji::normalize::normalize;
+use khiin_ji::normalize::Normalization;
+use khiin_ji::IsHanji;
 use khiin_protos::command::*;
+use khiin_protos::config::AppAnnotationMode;
+use khiin_protos::config::AppAsciiKhin;
+use khiin_protos::config::AppAsciiSpelling;
+use khiin_protos::config::AppHanjiVariant;
 use khiin_protos::config::AppInputMode;
+use khiin_protos::config::AppKeyboardLayout;
 use khiin_protos::config::AppKhinMode;
+use khiin_protos::config::AppNormalization;
 use khiin_protos::config::AppOutputMode;
+use khiin_protos::config::AppScriptPreference;
 use khiin_protos::config::BoolValue;
 
 use crate::buffer::BufferMgr;
+use crate::config::AnnotationMode;
 use crate::config::Config;
+use crate::config::HanjiVariant;
 use crate::config::InputMode;
+use crate::config::KeyboardLayout;
 use crate::config::KhinMode;
 use crate::config::OutputMode;
+use crate::config::ScriptPreference;
 use crate::config::ToneMode;
 use crate::data::dictionary::Dictionary;
+use crate::data::HanjiVariants;
+use crate::data::Snapshot;
+use crate::data::Snippets;
 use crate::db::Database;
+use crate::input::converter;
+use crate::input::fuzzy::Confusion;
+use crate::input::tps_keyboard::TpsKeyboard;
 
 pub struct Engine {
     buffer_mgr: BufferMgr,
     inner: EngInner,
+    tps_keys: TpsKeyboard,
 }
 
 pub(crate) struct EngInner {
     pub(crate) db: Database,
     pub(crate) dict: Dictionary,
     pub(crate) conf: Config,
+    pub(crate) snapshot: Option<Snapshot>,
+    pub(crate) variants: HanjiVariants,
+    pub(crate) snippets: Snippets,
+}
+
+impl EngInner {
+    /// Switches the tone mode, rebuilding the dictionary from the matching
+    /// key sequences. The new dictionary is built before anything is
+    /// replaced, so on error the engine keeps working in the old mode.
+    pub(crate) fn set_tone_mode(&mut self, tone_mode: ToneMode) -> Result<()> {
+        if self.conf.tone_mode() == tone_mode {
+            return Ok(());
+        }
+
+        let snapshot = self.snapshot.as_ref();
+        self.dict = load_dictionary(&self.db, snapshot, tone_mode)?;
+        self.conf.set_tone_mode(tone_mode);
+        log::debug!("Dictionary rebuilt for new tone mode");
+        Ok(())
+    }
+}
+
+fn load_dictionary(
+    db: &Database,
+    snapshot: Option<&Snapshot>,
+    tone_mode: ToneMode,
+) -> Result<Dictionary> {
+    match snapshot {
+        Some(snapshot) => Dictionary::from_snapshot(snapshot, tone_mode),
+        None => Dictionary::new(db, tone_mode),
+    }
 }
 
 impl Engine {
+    /// Loads the database at `filename`, and the `.snapshot` file next to it
+    /// if there is one (see `with_snapshot`)
     pub fn new<P>(filename: P) -> Option<Engine>
     where
         P: AsRef<Path> + Debug + Clone,
+    {
+        let snapshot_file = filename.as_ref().with_extension("snapshot");
+        if snapshot_file.exists() {
+            let engine = Self::with_snapshot(filename.clone(), &snapshot_file);
+            if engine.is_some() {
+                return engine;
+            }
+            log::warn!("Unable to load snapshot: {:?}", snapshot_file);
+        }
+        Self::from_database(filename)
+    }
+
+    /// Builds the word index from the database alone
+    pub fn from_database<P>(filename: P) -> Option<Engine>
+    where
+        P: AsRef<Path> + Debug + Clone,
+    {
+        let db = Database::new(filename.clone()).ok()?;
+        log::debug!("Database loaded from: {:?}", filename);
+        Self::from_parts(db, None)
+    }
+
+    /// Loads the word index from a snapshot written by `dbgen` instead of
+    /// building it from the database, which skips reading and costing every
+    /// key sequence at startup. Conversions are still read from the database,
+    /// which is loaded into memory as in `from_database`.
+    pub fn with_snapshot<P, Q>(filename: P, snapshot_file: Q) -> Option<Engine>
+    where
+        P: AsRef<Path> + Debug + Clone,
+        Q: AsRef<Path> + Debug,
     {
         let db = Database::new(filename.clone()).ok()?;
         log::debug!("Database loaded from: {:?}", filename);
-        let dict = Dictionary::new(&db, ToneMode::Numeric).ok()?;
+        let snapshot = Snapshot::open(&snapshot_file).ok()?;
+        log::debug!("Snapshot loaded from: {:?}", snapshot_file);
+        Self::from_parts(db, Some(snapshot))
+    }
+
+    /// Creates an engine from a database image held in memory, for hosts
+    /// without a filesystem (e.g. WebAssembly).
+    pub fn from_bytes(bytes: &[u8]) -> Option<Engine> {
+        let db = Database::from_bytes(bytes).ok()?;
+        log::debug!("Database loaded from {} bytes", bytes.len());
+        Self::from_parts(db, None)
+    }
+
+    fn from_parts(db: Database, snapshot: Option<Snapshot>) -> Option<Engine> {
+        let conf = Config::new();
+        let dict =
+            load_dictionary(&db, snapshot.as_ref(), conf.tone_mode()).ok()?;
         log::debug!("Dictionary initialized");
+        let variants = HanjiVariants::new(&db).unwrap_or_else(|e| {
+            log::warn!("Hanji variants not loaded: {}", e);
+            HanjiVariants::default()
+        });
+        let snippets = db
+            .select_snippets()
+            .and_then(Snippets::from_pairs)
+            .unwrap_or_else(|e| {
+                log::warn!("Snippets not loaded: {}", e);
+                Snippets::default()
+            });
 
         Some(Engine {
             buffer_mgr: BufferMgr::new(),
             inner: EngInner {
                 db,
                 dict,
-                conf: Config::new(),
+                conf,
+                snapshot,
+                variants,
+                snippets,
             },
+            tps_keys: TpsKeyboard::new(),
         })
     }
 
@@ -85,6 +203,13 @@ impl Engine {
             CommandType::CMD_LIST_EMOJIS => self.on_list_emojis(req),
             CommandType::CMD_RESET_USER_DATA => self.on_reset_user_data(req),
             CommandType::CMD_SHUTDOWN => self.on_shutdown(req),
+            CommandType::CMD_LIST_KEY_SEQUENCES => {
+                self.on_list_key_sequences(req)
+            },
+            CommandType::CMD_LIST_SNIPPETS => self.on_list_snippets(req),
+            CommandType::CMD_ADD_SNIPPETS => self.on_add_snippets(req),
+            CommandType::CMD_REMOVE_SNIPPETS => self.on_remove_snippets(req),
+            CommandType::CMD_SET_SNIPPETS => self.on_set_snippets(req),
         };
 
         if let Ok(res) = res {
@@ -99,20 +224,45 @@ impl Engine {
             .map_err(|_| Error::msg("Failed to write protobuf bytes"))
     }
 
+    /// Converts `raw` in a single pass, the same way the engine guesses a
+    /// conversion while typing. The input buffer is not touched.
+    pub fn convert(&self, raw: &str) -> Result<String> {
+        let mut composition = converter::convert_guess(&self.inner, raw)?;
+        composition.autospace();
+        Ok(composition.display_text())
+    }
+
+    /// Splits `raw` into the most likely sequence of dictionary words.
+    pub fn segment(&self, raw: &str) -> Result<Vec<String>> {
+        self.inner.dict.segment(raw)
+    }
+
+    /// The keys to type `word` in the current tone mode and key
+    /// configuration, paired with each lomaji reading. A Hanji word is
+    /// looked up in the dictionary, and lomaji is used as it is.
+    pub fn key_sequences(&self, word: &str) -> Result<Vec<(String, String)>> {
+        let readings = if contains_hanji(word) {
+            self.inner.db.select_readings(word)?
+        } else {
+            vec![word.to_string()]
+        };
+
+        Ok(readings
+            .into_iter()
+            .map(|reading| {
+                let keys = converter::lomaji_to_keys(&self.inner, &reading);
+                (reading, keys)
+            })
+            .collect())
+    }
+
     fn on_send_key(&mut self, req: Request) -> Result<Response> {
         log::debug!("Engine::on_send_key");
         match req.key_event.special_key.enum_value_or_default() {
             SpecialKey::SK_NONE => {
                 let ch = ascii_char_from_i32(req.key_event.key_code);
                 if let Some(ch) = ch {
-                    // if ch is number
-                    if ch.is_ascii_digit()
-                        && self.inner.conf.input_mode() == InputMode::Classic
-                        && self.buffer_mgr.is_focused()
-                    {
-                        // Convert key to index: 1->0, 2->1, ..., 9->8, 0->9
-                        let digit = ch.to_digit(10).unwrap() as usize;
-                        let idx = if digit == 0 { 9 } else { digit - 1 };
+                    if let Some(idx) = self.selection_index(ch) {
                         self.buffer_mgr
                             .focus_candidate_by_index(&self.inner, idx);
                         // check is candidate is action
@@ -124,13 +274,18 @@ impl Engine {
                         }
                         return self.on_commit(req);
                     } else {
-                        self.buffer_mgr.insert(&self.inner, ch)?;
-                        if self.buffer_mgr.edit_state() == EditState::ES_EMPTY {
+                        self.insert_key(ch)?;
+                        if self.buffer_mgr.edit_state() == EditState::ES_EMPTY
+                            && !self.tps_keys.is_composing()
+                        {
                             return self.on_commit_all(req);
                         }
                     }
                 }
             },
+            SpecialKey::SK_SPACE if self.tps_keys.is_composing() => {
+                self.insert_key(' ')?;
+            },
             SpecialKey::SK_SPACE => {
                 if (self.inner.conf.input_mode() == InputMode::Classic) {
                     if (req.key_event.modifier_keys.contains(
@@ -157,7 +312,9 @@ impl Engine {
             },
             SpecialKey::SK_ESC => {},
             SpecialKey::SK_BACKSPACE => {
-                self.buffer_mgr.pop(&self.inner)?;
+                if !self.tps_keys.pop() {
+                    self.buffer_mgr.pop(&self.inner)?;
+                }
             },
             SpecialKey::SK_TAB => {
                 if (req.key_event.modifier_keys.contains(
@@ -194,23 +351,48 @@ impl Engine {
         Err(anyhow!("Not implemented"))
     }
 
+    /// Inserts `key` as read on the configured keyboard layout
+    fn insert_key(&mut self, key: char) -> Result<()> {
+        if self.inner.conf.keyboard_layout() == KeyboardLayout::Lomaji {
+            return self.buffer_mgr.insert(&self.inner, key);
+        }
+
+        match self.tps_keys.on_key(&self.inner.conf, key) {
+            Some(keys) => {
+                for ch in keys.chars() {
+                    self.buffer_mgr.insert(&self.inner, ch)?;
+                }
+                Ok(())
+            },
+            None => self.buffer_mgr.insert(&self.inner, key),
+        }
+    }
+
     fn on_reset(&mut self, req: Request) -> Result<Response> {
         self.buffer_mgr.reset()?;
+        self.tps_keys.reset();
         Ok(Response::new())
     }
 
     fn on_commit_all(&mut self, req: Request) -> Result<Response> {
+        let words = if self.buffer_mgr.is_focused() {
+            self.buffer_mgr.words_to_commit()
+        } else {
+            Vec::new()
+        };
         let committed_text = self.buffer_mgr.commit_all(&self.inner)?;
         let mut response = Response::default();
         self.attach_buffer_data(&mut response)?;
-        response.committed_text = committed_text;
+        response.committed_text = self.normalize_output(&committed_text);
         response.committed = true;
         self.buffer_mgr.reset()?;
+        self.tps_keys.reset();
         if let Some(ref mut p) = response.preedit.as_mut() {
             p.caret = 0;
             p.focused_caret = 0;
         }
         response.edit_state = EditState::ES_EMPTY.into();
+        self.attach_next_words(words, &mut response)?;
         Ok(response)
     }
 
@@ -228,6 +410,7 @@ impl Engine {
     }
 
     fn on_commit(&mut self, req: Request) -> Result<Response> {
+        let words = self.buffer_mgr.words_to_commit();
         if (self.inner.conf.input_mode() == InputMode::Classic) {
             // only classic mode need comosite remainder
             let committed_text = self
@@ -235,32 +418,67 @@ impl Engine {
                 .commit_candidate_and_comosite_remainder(&self.inner)?;
             let mut response = Response::default();
             self.attach_buffer_data(&mut response)?;
-            response.committed_text = committed_text;
+            response.committed_text = self.normalize_output(&committed_text);
             response.committed = true;
+            self.attach_next_words(words, &mut response)?;
             return Ok(response);
         }
         let mut response = Response::new();
         response.committed = true;
         self.attach_preedit(&mut response)?;
         self.buffer_mgr.reset()?;
+        self.tps_keys.reset();
         if let Some(ref mut p) = response.preedit.as_mut() {
             p.caret = 0;
             p.focused_caret = 0;
         }
         response.edit_state = EditState::ES_EMPTY.into();
+        self.attach_next_words(words, &mut response)?;
         Ok(response)
     }
 
-    fn on_select_candidate(&self, req: Request) -> Result<Response> {
-        Err(anyhow!("Not implemented"))
+    /// Commits one of the next words offered after the last commit
+    fn on_select_candidate(&mut self, req: Request) -> Result<Response> {
+        if self.buffer_mgr.edit_state() != EditState::ES_EMPTY {
+            return Err(anyhow!("Not implemented"));
+        }
+
+        let word = self
+            .buffer_mgr
+            .next_word(req.candidate_id as usize)
+            .ok_or(anyhow!("No next word {}", req.candidate_id))?
+            .to_string();
+
+        let after_hanji = self
+            .buffer_mgr
+            .last_word()
+            .and_then(|last| last.chars().last())
+            .is_some_and(|ch| ch.is_hanji());
+        let mut committed_text = String::new();
+        if !(after_hanji && word.chars().next().is_some_and(|c| c.is_hanji())) {
+            committed_text.push(' ');
+        }
+        committed_text.push_str(&word);
+
+        let mut response = Response::default();
+        response.committed_text = self.normalize_output(&committed_text);
+        response.committed = true;
+        response.edit_state = EditState::ES_EMPTY.into();
+        self.attach_next_words(vec![word], &mut response)?;
+        Ok(response)
     }
 
-    fn on_focus_candidate(&self, req: Request) -> Result<Response> {
-        Err(anyhow!("Not implemented"))
+    fn on_focus_candidate(&mut self, req: Request) -> Result<Response> {
+        self.buffer_mgr
+            .focus_candidate_by_id(&self.inner, req.candidate_id as usize)?;
+        let mut res = Response::new();
+        self.attach_buffer_data(&mut res)?;
+        Ok(res)
     }
 
     fn on_switch_input_mode(&mut self, req: Request) -> Result<Response> {
         self.buffer_mgr.reset();
+        self.tps_keys.reset();
         match req.config.input_mode.enum_value_or_default() {
             AppInputMode::CONTINUOUS => {
                 self.inner.conf.set_input_mode(InputMode::Continuous)
@@ -277,6 +495,7 @@ impl Engine {
 
     fn on_switch_output_mode(&mut self, req: Request) -> Result<Response> {
         self.buffer_mgr.reset();
+        self.tps_keys.reset();
         match req.config.output_mode.enum_value_or_default() {
             AppOutputMode::LOMAJI => {
                 self.inner.conf.set_output_mode(OutputMode::Lomaji)
@@ -284,6 +503,12 @@ impl Engine {
             AppOutputMode::HANJI => {
                 self.inner.conf.set_output_mode(OutputMode::Hanji)
             },
+            AppOutputMode::TPS => {
+                self.inner.conf.set_output_mode(OutputMode::Tps)
+            },
+            AppOutputMode::ASCII => {
+                self.inner.conf.set_output_mode(OutputMode::Ascii)
+            },
         }
         Ok(Response::new())
     }
@@ -302,6 +527,7 @@ impl Engine {
 
     fn on_set_config(&mut self, req: Request) -> Result<Response> {
         self.buffer_mgr.reset();
+        self.tps_keys.reset();
         match req.config.input_mode.enum_value_or_default() {
             AppInputMode::CONTINUOUS => {
                 self.inner.conf.set_input_mode(InputMode::Continuous)
@@ -321,6 +547,12 @@ impl Engine {
             AppOutputMode::HANJI => {
                 self.inner.conf.set_output_mode(OutputMode::Hanji)
             },
+            AppOutputMode::TPS => {
+                self.inner.conf.set_output_mode(OutputMode::Tps)
+            },
+            AppOutputMode::ASCII => {
+                self.inner.conf.set_output_mode(OutputMode::Ascii)
+            },
         }
 
         match req.config.khin_mode.enum_value_or_default() {
@@ -333,24 +565,179 @@ impl Engine {
             },
         }
 
+        match req.config.annotation_mode.enum_value_or_default() {
+            AppAnnotationMode::ANNOTATE_HINT => {
+                self.inner.conf.set_annotation_mode(AnnotationMode::Hint)
+            },
+            AppAnnotationMode::ANNOTATE_READING => {
+                self.inner.conf.set_annotation_mode(AnnotationMode::Reading)
+            },
+            AppAnnotationMode::ANNOTATE_GLOSS => {
+                self.inner.conf.set_annotation_mode(AnnotationMode::Gloss)
+            },
+            AppAnnotationMode::ANNOTATE_TPS => {
+                self.inner.conf.set_annotation_mode(AnnotationMode::Tps)
+            },
+            AppAnnotationMode::ANNOTATE_IPA => {
+                self.inner.conf.set_annotation_mode(AnnotationMode::Ipa)
+            },
+            AppAnnotationMode::ANNOTATE_NONE => {
+                self.inner.conf.set_annotation_mode(AnnotationMode::Off)
+            },
+        }
+
+        match req.config.script_preference.enum_value_or_default() {
+            AppScriptPreference::SCRIPT_ANY => {
+                self.inner.conf.set_script_preference(ScriptPreference::Any)
+            },
+            AppScriptPreference::SCRIPT_HANJI => self
+                .inner
+                .conf
+                .set_script_preference(ScriptPreference::Hanji),
+            AppScriptPreference::SCRIPT_HANLO => self
+                .inner
+                .conf
+                .set_script_preference(ScriptPreference::HanLo),
+        }
+
+        match req.config.keyboard_layout.enum_value_or_default() {
+            AppKeyboardLayout::KEYBOARD_LOMAJI => {
+                self.inner.conf.set_keyboard_layout(KeyboardLayout::Lomaji)
+            },
+            AppKeyboardLayout::KEYBOARD_TPS => {
+                self.inner.conf.set_keyboard_layout(KeyboardLayout::Tps)
+            },
+        }
+
+        match req.config.ascii_spelling.enum_value_or_default() {
+            AppAsciiSpelling::SPELLING_POJ => {
+                self.inner.conf.set_ascii_spelling(Spelling::Poj)
+            },
+            AppAsciiSpelling::SPELLING_TAILO => {
+                self.inner.conf.set_ascii_spelling(Spelling::TaiLo)
+            },
+        }
+
+        match req.config.ascii_khin.enum_value_or_default() {
+            AppAsciiKhin::ASCII_KHIN_HYPHENS => {
+                self.inner.conf.set_ascii_khin(KhinMark::Hyphens)
+            },
+            AppAsciiKhin::ASCII_KHIN_ZERO => {
+                self.inner.conf.set_ascii_khin(KhinMark::Zero)
+            },
+        }
+
+        match req.config.normalization.enum_value_or_default() {
+            AppNormalization::NORMALIZE_FONT_SAFE => {
+                self.inner.conf.set_normalization(Normalization::FontSafe)
+            },
+            AppNormalization::NORMALIZE_NFC => {
+                self.inner.conf.set_normalization(Normalization::Nfc)
+            },
+            AppNormalization::NORMALIZE_NFD => {
+                self.inner.conf.set_normalization(Normalization::Nfd)
+            },
+        }
+
+        match req.config.hanji_variant.enum_value_or_default() {
+            AppHanjiVariant::HANJI_STANDARD => {
+                self.inner.conf.set_hanji_variant(HanjiVariant::Standard)
+            },
+            AppHanjiVariant::HANJI_COMMON => {
+                self.inner.conf.set_hanji_variant(HanjiVariant::Common)
+            },
+        }
+
+        if let Some(standard_only) = req.config.standard_chars_only.as_ref() {
+            self.inner.conf.set_standard_only(standard_only.value);
+        }
+
+        if let Some(hide_lomaji) = req.config.hide_lomaji_candidates.as_ref() {
+            self.inner.conf.set_lomaji_hidden(hide_lomaji.value);
+        }
+
+        if let Some(autokhin) = req.config.autokhin.as_ref() {
+            self.inner.conf.set_autokhin(autokhin.value);
+        }
+
         // let mut telex_enabled = BoolValue::new();
         if let Some(telex_enabled) = req.config.telex_enabled.as_ref() {
-            if telex_enabled.value {
-                self.inner.conf.set_tone_mode(ToneMode::Telex)
+            let key_config = &req.config.key_config;
+            if !telex_enabled.value {
+                self.inner.set_tone_mode(ToneMode::Numeric)?
+            } else if key_config.use_digits_as_fallback.value {
+                self.inner.set_tone_mode(ToneMode::Hybrid)?
             } else {
-                self.inner.conf.set_tone_mode(ToneMode::Numeric)
+                self.inner.set_tone_mode(ToneMode::Telex)?
             }
         }
 
+        if let Some(fuzzy_enabled) = req.config.fuzzy_enabled.as_ref() {
+            self.inner.conf.set_fuzzy_enabled(fuzzy_enabled.value);
+        }
+
+        if !req.config.fuzzy_confusions.is_empty() {
+            let confusions = req
+                .config
+                .fuzzy_confusions
+                .iter()
+                .filter_map(|value| match Confusion::parse(value) {
+                    Ok(confusion) => Some(confusion),
+                    Err(e) => {
+                        log::debug!("{}", e);
+                        None
+                    },
+                })
+                .collect();
+            self.inner.conf.set_confusions(confusions);
+        }
+
+        if let Some(next_word_enabled) = req.config.next_word_enabled.as_ref() {
+            self.inner
+                .conf
+                .set_next_word_enabled(next_word_enabled.value);
+        }
+
+        if let Some(prediction_enabled) = req.config.prediction_enabled.as_ref()
+        {
+            self.inner
+                .conf
+                .set_prediction_enabled(prediction_enabled.value);
+        }
+
+        if let Some(abbreviation_enabled) =
+            req.config.abbreviation_enabled.as_ref()
+        {
+            self.inner
+                .conf
+                .set_abbreviation_enabled(abbreviation_enabled.value);
+        }
+
+        if req.config.page_size > 0 {
+            self.inner.conf.set_page_size(req.config.page_size as usize);
+        }
+
+        if req.config.prediction_limit > 0 {
+            self.inner
+                .conf
+                .set_prediction_limit(req.config.prediction_limit as usize);
+        }
+
+        if req.config.next_word_limit > 0 {
+            self.inner
+                .conf
+                .set_next_word_limit(req.config.next_word_limit as usize);
+        }
+
         // set key configuration
         if let Some(key_config) = req.config.key_config.as_ref() {
-            self.inner.conf.set_t2_key(key_config.telex_t2.chars().next().unwrap_or(self.inner.conf.t2()));
-            self.inner.conf.set_t3_key(key_config.telex_t3.chars().next().unwrap_or(self.inner.conf.t3()));
-            self.inner.conf.set_t5_key(key_config.telex_t5.chars().next().unwrap_or(self.inner.conf.t5()));
-            self.inner.conf.set_t6_key(key_config.telex_t6.chars().next().unwrap_or(self.inner.conf.t6()));
-            self.inner.conf.set_t7_key(key_config.telex_t7.chars().next().unwrap_or(self.inner.conf.t7()));
-            self.inner.conf.set_t8_key(key_config.telex_t8.chars().next().unwrap_or(self.inner.conf.t8()));
-            self.inner.conf.set_t9_key(key_config.telex_t9.chars().next().unwrap_or(self.inner.conf.t9()));
+            self.inner.conf.set_t2_key(key_config.telex_t2.chars().next().unwrap_or(self.inner.conf.key_config().t2));
+            self.inner.conf.set_t3_key(key_config.telex_t3.chars().next().unwrap_or(self.inner.conf.key_config().t3));
+            self.inner.conf.set_t5_key(key_config.telex_t5.chars().next().unwrap_or(self.inner.conf.key_config().t5));
+            self.inner.conf.set_t6_key(key_config.telex_t6.chars().next().unwrap_or(self.inner.conf.key_config().t6));
+            self.inner.conf.set_t7_key(key_config.telex_t7.chars().next().unwrap_or(self.inner.conf.key_config().t7));
+            self.inner.conf.set_t8_key(key_config.telex_t8.chars().next().unwrap_or(self.inner.conf.key_config().t8));
+            self.inner.conf.set_t9_key(key_config.telex_t9.chars().next().unwrap_or(self.inner.conf.key_config().t9));
             self.inner.conf.set_khin_key(key_config.telex_khin.chars().next().unwrap_or(self.inner.conf.khin()));
             self.inner.conf.set_hyphen_key(key_config.alt_hyphen.chars().next().unwrap_or(self.inner.conf.hyphen()));
             self.inner.conf.set_done_key(key_config.done.chars().next().unwrap_or(self.inner.conf.done()));
@@ -375,13 +762,74 @@ impl Engine {
         Err(anyhow!("Not implemented"))
     }
 
+    fn on_list_key_sequences(&self, req: Request) -> Result<Response> {
+        let mut list = CandidateList::new();
+        for (id, (reading, keys)) in
+            self.key_sequences(&req.query)?.into_iter().enumerate()
+        {
+            let mut cand = Candidate::new();
+            cand.id = id as i32;
+            cand.value = reading;
+            cand.key = keys;
+            list.candidates.push(cand);
+        }
+
+        let mut res = Response::new();
+        res.candidate_list = Some(list).into();
+        Ok(res)
+    }
+
+    fn on_list_snippets(&self, req: Request) -> Result<Response> {
+        let mut res = Response::new();
+        for (trigger, value) in self.inner.snippets.iter() {
+            let mut snippet = Snippet::new();
+            snippet.trigger = trigger.to_string();
+            snippet.value = value.to_string();
+            res.snippets.push(snippet);
+        }
+        Ok(res)
+    }
+
+    fn on_add_snippets(&mut self, req: Request) -> Result<Response> {
+        let pairs = Snippets::normalize(snippet_pairs(&req))?;
+        self.inner.db.insert_snippets(&pairs)?;
+        self.inner.snippets.extend(pairs)?;
+        self.on_list_snippets(req)
+    }
+
+    fn on_remove_snippets(&mut self, req: Request) -> Result<Response> {
+        for snippet in req.snippets.iter() {
+            let trigger = snippet.trigger.to_ascii_lowercase();
+            let value = Some(snippet.value.as_str()).filter(|v| !v.is_empty());
+            self.inner.db.delete_snippets(Some(&trigger), value)?;
+            self.inner.snippets.remove(&trigger, value);
+        }
+        self.on_list_snippets(req)
+    }
+
+    /// Replaces every snippet with those in `req`. The old snippets are kept
+    /// if any in `req` are incomplete.
+    fn on_set_snippets(&mut self, req: Request) -> Result<Response> {
+        let pairs = Snippets::normalize(snippet_pairs(&req))?;
+        self.inner.db.replace_snippets(&pairs)?;
+        self.inner.snippets = Snippets::from_pairs(pairs)?;
+        self.on_list_snippets(req)
+    }
+
+    /// Committed text in the configured Unicode normalization
+    fn normalize_output(&self, text: &str) -> String {
+        normalize(text, self.inner.conf.normalization())
+    }
+
     fn attach_preedit(&self, res: &mut Response) -> Result<()> {
-        res.preedit = Some(self.buffer_mgr.build_preedit()).into();
+        res.preedit =
+            Some(self.buffer_mgr.build_preedit(&self.inner.conf)).into();
         Ok(())
     }
 
     fn attach_candidate_list(&self, res: &mut Response) -> Result<()> {
-        res.candidate_list = Some(self.buffer_mgr.get_candidates()).into();
+        res.candidate_list =
+            Some(self.buffer_mgr.get_candidates(&self.inner.conf)).into();
         Ok(())
     }
 
@@ -390,6 +838,59 @@ impl Engine {
         Ok(())
     }
 
+    /// Learns the bigrams in `words`, which were just committed, and offers
+    /// the words most often typed next once the buffer is empty
+    fn attach_next_words(
+        &mut self,
+        words: Vec<String>,
+        res: &mut Response,
+    ) -> Result<()> {
+        if !self.inner.conf.is_next_word_enabled() || words.is_empty() {
+            return Ok(());
+        }
+
+        let mut lgram = self.buffer_mgr.last_word().map(String::from);
+        for word in words.into_iter() {
+            if let Some(lgram) = lgram.as_ref() {
+                self.inner.db.record_bigram(lgram, &word)?;
+            }
+            lgram = Some(word);
+        }
+
+        if let Some(last) = lgram.as_ref() {
+            if self.buffer_mgr.edit_state() == EditState::ES_EMPTY {
+                let next = self.inner.db.select_next_words(
+                    last,
+                    self.inner.conf.next_word_limit(),
+                )?;
+                self.buffer_mgr.set_next_words(next);
+                res.next_words = Some(self.buffer_mgr.get_next_words()).into();
+            }
+        }
+        self.buffer_mgr.set_last_word(lgram);
+
+        Ok(())
+    }
+
+    /// The candidate index a digit selects in Classic mode. Digits are
+    /// symbols on the TPS layout, and digits past the page are typed as
+    /// input.
+    fn selection_index(&self, ch: char) -> Option<usize> {
+        if self.inner.conf.input_mode() != InputMode::Classic
+            || self.inner.conf.keyboard_layout() != KeyboardLayout::Lomaji
+            || !self.buffer_mgr.is_focused()
+        {
+            return None;
+        }
+
+        // Convert key to index: 1->0, 2->1, ..., 9->8, 0->9
+        let idx = match ch.to_digit(10)? {
+            0 => 9,
+            digit => digit as usize - 1,
+        };
+        (idx < self.inner.conf.page_size()).then_some(idx)
+    }
+
     fn attach_buffer_data(&self, res: &mut Response) -> Result<()> {
         self.attach_preedit(res)?;
         self.attach_candidate_list(res)?;
@@ -397,6 +898,13 @@ impl Engine {
     }
 }
 
+fn snippet_pairs(req: &Request) -> Vec<(String, String)> {
+    req.snippets
+        .iter()
+        .map(|s| (s.trigger.clone(), s.value.clone()))
+        .collect()
+}
+
 fn ascii_char_from_i32(ch: i32) -> Option<char> {
     let ch = ch as u32;
     if let Some(ch) = char::from_u32(ch) {
@@ -420,6 +928,12 @@ mod tests {
         assert!(engine.is_some());
     }
 
+    #[test]
+    fn it_loads_from_bytes() {
+        let bytes = std::fs::read(debug_db_path()).unwrap();
+        assert!(Engine::from_bytes(&bytes).is_some());
+    }
+
     #[test]
     fn it_handles_send_key_commands() -> Result<()> {
         let mut engine = get_engine().unwrap();
@@ -448,4 +962,441 @@ mod tests {
         assert_eq!(res.preedit.segments.len(), 1);
         Ok(())
     }
+
+    #[test]
+    fn it_converts_and_segments_text() -> Result<()> {
+        let engine = get_engine().unwrap();
+        assert_eq!(engine.segment("goa")?, vec!["goa"]);
+        assert!(!engine.convert("goa")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn it_rebuilds_the_dictionary_on_tone_mode_switch() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        assert_eq!(engine.segment("goaslangf")?, vec!["goas", "langf"]);
+
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false)
+        }))?;
+        assert_eq!(engine.segment("goa2lang5")?, vec!["goa2", "lang5"]);
+
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(true)
+        }))?;
+        assert_eq!(engine.segment("goaslangf")?, vec!["goas", "langf"]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_switches_tone_mode_with_a_snapshot() -> Result<()> {
+        let path = std::env::temp_dir().join("khiin_engine_test.snapshot");
+        Snapshot::write(&get_db(), &path)?;
+        let mut engine = Engine::with_snapshot(debug_db_path(), &path).unwrap();
+        assert_eq!(engine.segment("goaslangf")?, vec!["goas", "langf"]);
+
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false)
+        }))?;
+        assert_eq!(engine.segment("goa2lang5")?, vec!["goa2", "lang5"]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_accepts_digits_and_telex_in_hybrid_mode() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(true);
+            c.key_config.mut_or_insert_default().use_digits_as_fallback =
+                mock_bool(true);
+        }))?;
+        assert_eq!(engine.segment("goaslang5")?, vec!["goas", "lang5"]);
+        assert_eq!(engine.segment("goa2langf")?, vec!["goa2", "langf"]);
+        assert!(engine.inner.conf.is_tone_char('s'));
+        assert!(engine.inner.conf.is_tone_char('2'));
+        assert!(!engine.inner.conf.is_tone_char('4'));
+
+        for keys in ["hos", "ho2"] {
+            engine.on_reset(Request::new())?;
+            let mut res = Response::new();
+            for ch in keys.chars() {
+                res = engine.on_send_key(mock_send_key_request(ch))?;
+            }
+            assert_eq!(res.candidate_list.candidates[0].value, "好");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn it_types_digits_past_the_candidate_page() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false)
+        }))?;
+        engine.inner.conf.set_input_mode(InputMode::Classic);
+        engine.inner.conf.set_page_size(5);
+
+        for ch in "ho".chars() {
+            engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        engine.buffer_mgr.focus_next_candidate(&engine.inner)?;
+        assert!(engine.buffer_mgr.is_focused());
+
+        let res = engine.on_send_key(mock_send_key_request('7'))?;
+        assert!(!res.committed);
+        assert_eq!(res.preedit.segments[0].value, "ho7");
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_keys_on_the_tps_layout() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false)
+        }))?;
+        engine.inner.conf.set_keyboard_layout(KeyboardLayout::Tps);
+
+        let mut res = Response::new();
+        for ch in "ru8c71,[".chars() {
+            res = engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        assert_eq!(res.preedit.segments[0].value, "chia̍h pn̄g");
+        let cands = &res.candidate_list.candidates;
+        assert!(cands.iter().any(|c| c.value == "食飯"));
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_telex_tones_on_the_tps_layout() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(true)
+        }))?;
+        engine.inner.conf.set_keyboard_layout(KeyboardLayout::Tps);
+
+        fn preedit(engine: &mut Engine, keys: &str) -> Result<String> {
+            let mut res = Response::new();
+            for ch in keys.chars() {
+                res = engine.on_send_key(mock_send_key_request(ch))?;
+            }
+            engine.on_reset(Request::new())?;
+            Ok(res.preedit.segments[0].value.clone())
+        }
+        // Tones 1 and 4 add no digit key
+        let tps = preedit(&mut engine, "eu8- e8c ")?;
+        assert!(!tps.contains(|c: char| c.is_ascii_digit()));
+        engine
+            .inner
+            .conf
+            .set_keyboard_layout(KeyboardLayout::Lomaji);
+        assert_eq!(tps, preedit(&mut engine, "kiannkah")?);
+        Ok(())
+    }
+
+    #[test]
+    fn it_types_tps_digit_keys_while_a_candidate_is_focused() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false)
+        }))?;
+        engine.inner.conf.set_input_mode(InputMode::Classic);
+        engine.inner.conf.set_keyboard_layout(KeyboardLayout::Tps);
+
+        for ch in "ru8c7".chars() {
+            engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        engine.buffer_mgr.focus_next_candidate(&engine.inner)?;
+        assert!(engine.buffer_mgr.is_focused());
+
+        // ㄅ, not the first candidate
+        let res = engine.on_send_key(mock_send_key_request('1'))?;
+        assert!(!res.committed);
+        assert!(res.preedit.segments[0].value.ends_with('p'));
+        Ok(())
+    }
+
+    #[test]
+    fn it_normalizes_the_preedit() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false)
+        }))?;
+
+        let mut res = Response::new();
+        for ch in "hou2".chars() {
+            res = engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        assert_eq!(res.preedit.segments[0].value, "h\u{f3}\u{358}");
+        assert_eq!(res.preedit.caret, 3);
+
+        engine.on_reset(Request::new())?;
+        engine.inner.conf.set_normalization(Normalization::Nfd);
+        for ch in "hou2".chars() {
+            res = engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        assert_eq!(res.preedit.segments[0].value, "ho\u{301}\u{358}");
+        assert_eq!(res.preedit.caret, 4);
+
+        let res = engine.on_commit(Request::new())?;
+        assert_eq!(res.preedit.segments[0].value, "ho\u{301}\u{358}");
+        Ok(())
+    }
+
+    #[test]
+    fn it_lists_key_sequences() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(true)
+        }))?;
+        engine.inner.conf.set_khin_mode(KhinMode::Hyphen);
+        let keys = engine.key_sequences("khòaⁿ-·kìⁿ")?;
+        assert_eq!(keys[0].1, "khoannfvkinnf");
+
+        engine.inner.conf.set_t3_key('x');
+        let keys = engine.key_sequences("khòaⁿ-·kìⁿ")?;
+        assert_eq!(keys[0].1, "khoannxvkinnx");
+
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false)
+        }))?;
+        let mut req = Request::new();
+        req.query = "食飯".to_string();
+        let res = engine.on_list_key_sequences(req)?;
+        let cand = &res.candidate_list.candidates[0];
+        assert_eq!(cand.value, "chia̍h pn̄g");
+        assert_eq!(cand.key, "chiah8png7");
+
+        let mut res = Response::new();
+        for ch in cand.key.chars() {
+            res = engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        assert_eq!(res.candidate_list.candidates[0].value, "食飯");
+        Ok(())
+    }
+
+    #[test]
+    fn it_offers_corrections_as_fallback_candidates() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false);
+            c.fuzzy_enabled = mock_bool(true);
+            c.fuzzy_confusions = vec!["ts/ch".to_string()];
+        }))?;
+        assert!(engine.inner.conf.is_fuzzy_enabled());
+        assert_eq!(engine.inner.conf.confusions().len(), 1);
+
+        let mut res = Response::new();
+        for ch in "tsiah8".chars() {
+            res = engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        let cand = res
+            .candidate_list
+            .candidates
+            .iter()
+            .find(|c| c.value == "食")
+            .unwrap();
+        assert_eq!(
+            cand.category.enum_value_or_default(),
+            candidate::Category::CCAT_FALLBACK
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_predicts_words_from_a_prefix() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false);
+            c.prediction_enabled = mock_bool(true);
+            c.prediction_limit = 2;
+        }))?;
+        assert!(engine.inner.conf.is_prediction_enabled());
+
+        let mut res = Response::new();
+        for ch in "chia".chars() {
+            res = engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        let predicted: Vec<_> = res
+            .candidate_list
+            .candidates
+            .iter()
+            .filter(|c| {
+                c.category.enum_value_or_default()
+                    == candidate::Category::CCAT_PREDICTION
+            })
+            .collect();
+        assert!(!predicted.is_empty() && predicted.len() <= 2);
+        assert_ne!(
+            res.candidate_list.candidates[0]
+                .category
+                .enum_value_or_default(),
+            candidate::Category::CCAT_PREDICTION
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_matches_abbreviations() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(true);
+            c.output_mode = AppOutputMode::HANJI.into();
+            c.abbreviation_enabled = mock_bool(true);
+        }))?;
+        assert!(engine.inner.conf.is_abbreviation_enabled());
+
+        let mut res = Response::new();
+        for ch in "tg".chars() {
+            res = engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        let values: Vec<_> = res
+            .candidate_list
+            .candidates
+            .iter()
+            .map(|c| c.value.as_str())
+            .collect();
+        assert!(values.contains(&"台語"), "{:?}", values);
+
+        let idx = values.iter().position(|&v| v == "台語").unwrap();
+        engine
+            .buffer_mgr
+            .focus_candidate_by_index(&engine.inner, idx)?;
+        let res = engine.on_commit_all(Request::new())?;
+        assert_eq!(res.committed_text, "台語");
+        Ok(())
+    }
+
+    fn snippet(trigger: &str, value: &str) -> Snippet {
+        let mut snippet = Snippet::new();
+        snippet.trigger = trigger.to_string();
+        snippet.value = value.to_string();
+        snippet
+    }
+
+    #[test]
+    fn it_offers_snippets_first() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false)
+        }))?;
+        let mut req = Request::new();
+        req.snippets = vec![
+            snippet("Addr", "台北市中正區"),
+            snippet("sig", "Lí hó, Góa sī A-bêng"),
+        ];
+        let res = engine.on_set_snippets(req)?;
+        assert_eq!(res.snippets.len(), 2);
+        assert_eq!(res.snippets[0].trigger, "addr");
+
+        let mut res = Response::new();
+        for ch in "addr".chars() {
+            res = engine.on_send_key(mock_send_key_request(ch))?;
+        }
+        assert_eq!(res.candidate_list.candidates[0].value, "台北市中正區");
+
+        let mut req = Request::new();
+        req.snippets = vec![snippet("addr", "")];
+        let res = engine.on_remove_snippets(req)?;
+        assert_eq!(res.snippets.len(), 1);
+        assert_eq!(res.snippets[0].trigger, "sig");
+        let stored = engine.inner.db.select_snippets()?;
+        assert_eq!(stored, vec![("sig".into(), "Lí hó, Góa sī A-bêng".into())]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_keeps_snippets_when_a_set_request_is_incomplete() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        let mut req = Request::new();
+        req.snippets = vec![snippet("addr", "台北市中正區")];
+        engine.on_set_snippets(req)?;
+
+        let mut req = Request::new();
+        req.snippets = vec![snippet("sig", "A-bêng"), snippet("", "空")];
+        assert!(engine.on_set_snippets(req).is_err());
+
+        let res = engine.on_list_snippets(Request::new())?;
+        assert_eq!(res.snippets.len(), 1);
+        assert_eq!(res.snippets[0].value, "台北市中正區");
+        assert_eq!(engine.inner.db.select_snippets()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn it_offers_next_words_after_a_commit() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false);
+            c.next_word_enabled = mock_bool(true);
+        }))?;
+
+        let mut commit = |keys: &str| -> Result<Response> {
+            for ch in keys.chars() {
+                engine.on_send_key(mock_send_key_request(ch))?;
+            }
+            engine.on_commit(Request::new())
+        };
+        commit("chiah8")?;
+        commit("png7")?;
+        let res = commit("chiah8")?;
+        let next = &res.next_words.candidates;
+        assert_eq!(next[0].value, "pn̄g");
+
+        let res =
+            engine.on_select_candidate(mock_select_candidate_request(0))?;
+        assert!(res.committed);
+        assert_eq!(res.committed_text, " pn̄g");
+        Ok(())
+    }
+
+    #[test]
+    fn it_limits_next_words_separately_from_predictions() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false);
+            c.next_word_enabled = mock_bool(true);
+            c.prediction_limit = 1;
+            c.next_word_limit = 2;
+        }))?;
+
+        let mut commit = |keys: &str| -> Result<Response> {
+            for ch in keys.chars() {
+                engine.on_send_key(mock_send_key_request(ch))?;
+            }
+            engine.on_commit(Request::new())
+        };
+        for keys in ["chiah8", "png7", "chiah8", "te5", "chiah8", "ka"] {
+            commit(keys)?;
+        }
+        let res = commit("chiah8")?;
+        assert_eq!(res.next_words.candidates.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn it_focuses_a_candidate_by_id() -> Result<()> {
+        let mut engine = get_engine().unwrap();
+        engine.on_set_config(mock_set_config_request(|c| {
+            c.telex_enabled = mock_bool(false);
+            c.next_word_enabled = mock_bool(true);
+        }))?;
+        let res = engine.on_send_key(mock_send_key_request('a'))?;
+        let second = res.candidate_list.candidates[1].value.clone();
+
+        let res = engine.on_focus_candidate(mock_focus_candidate_request(1))?;
+        assert_eq!(res.candidate_list.focused, 1);
+        let res = engine.on_commit(Request::new())?;
+        assert!(res.committed);
+        let text: String = res
+            .preedit
+            .segments
+            .iter()
+            .map(|s| s.value.as_str())
+            .collect();
+        assert_eq!(text, second);
+
+        let res = engine.on_focus_candidate(mock_focus_candidate_request(99));
+        assert!(res.is_err());
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
 pub(crate) mod converter;
+pub(crate) mod fuzzy;
 pub(crate) mod parser;
 pub(crate) mod syllable;
+pub(crate) mod tps_keyboard;
 
 pub(crate) use parser::parse_longest_from_start;
 pub(crate) use parser::parse_whole_input;
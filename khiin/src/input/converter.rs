@@ -1,6 +1,11 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::collections::HashSet;
 
 use anyhow::Result;
+use khiin_ji::ascii::lomaji_to_ascii;
+use khiin_ji::numerals;
+use khiin_ji::tps::lomaji_to_tps;
 
 use crate::buffer::Buffer;
 use crate::buffer::BufferElement;
@@ -8,44 +13,331 @@ use crate::buffer::BufferElementEnum;
 use crate::buffer::KhiinElem;
 use crate::buffer::StringElem;
 use crate::config::Config;
+use crate::config::HanjiVariant;
 use crate::config::OutputMode;
 use crate::config::KhinMode;
+use crate::config::ScriptPreference;
 use crate::config::ToneMode;
 use crate::data::Dictionary;
 use crate::db::models::CaseType;
 use crate::db::models::InputType;
+use crate::db::models::KeyConversion;
+use crate::db::models::MIN_ABBREVIATION_LEN;
 use crate::db::Database;
 use crate::engine::EngInner;
 use crate::input::parser::SectionType;
 
+use super::fuzzy::find_corrections;
 use super::parse_longest_from_start;
 use super::parse_whole_input;
 use super::Syllable;
 
 use khiin_ji::lomaji::has_tone_letter;
+use khiin_ji::lomaji::key_to_tone;
+use khiin_ji::lomaji::poj_syl_to_key_sequences;
 use khiin_ji::lomaji::strip_khin;
 use khiin_ji::lomaji::strip_tone_diacritic;
+use khiin_ji::IsHanji;
 use khiin_ji::Tone;
 
+/// Shorter inputs match too many words to predict usefully
+const MIN_PREDICTION_LEN: usize = 2;
+
 pub(crate) fn get_candidates(
     engine: &EngInner,
     raw_buffer: &str,
 ) -> Result<Vec<Buffer>> {
+    if is_number_input(raw_buffer) {
+        return Ok(number_candidates(engine, raw_buffer));
+    }
+
     let (ty, query) = parse_longest_from_start(&engine.dict, raw_buffer);
 
-    match ty {
-        SectionType::Plaintext => Ok(Vec::new()),
-        SectionType::Hyphens => Ok(Vec::new()),
-        SectionType::Punct => Ok(Vec::new()),
-        SectionType::Splittable => candidates_for_splittable(engine, query),
+    let mut result = match ty {
+        SectionType::Plaintext => Vec::new(),
+        SectionType::Hyphens => Vec::new(),
+        SectionType::Punct => Vec::new(),
+        SectionType::Splittable => candidates_for_splittable(engine, query)?,
+    };
+    rank_by_script(&engine.conf, &mut result);
+
+    if engine.conf.is_abbreviation_enabled() {
+        let abbreviated = abbreviated_candidates(engine, raw_buffer, &result)?;
+        result.extend(abbreviated);
+    }
+
+    if engine.conf.is_prediction_enabled() {
+        // Shown after the best exact match, so they stay on the first page
+        let predicted = predicted_candidates(engine, raw_buffer, &result)?;
+        let at = result.len().min(1);
+        result.splice(at..at, predicted);
+    }
+
+    if engine.conf.is_fuzzy_enabled() {
+        let fuzzy = fuzzy_candidates(engine, raw_buffer, &result)?;
+        result.extend(fuzzy);
+    }
+
+    filter_by_script(&engine.conf, &mut result);
+    Ok(result)
+}
+
+/// Whether `raw` is, or starts to be, a number, date, time or money amount
+pub(crate) fn is_number_input(raw: &str) -> bool {
+    let rest = raw.strip_prefix('$').unwrap_or(raw);
+    if rest.is_empty() {
+        return !raw.is_empty();
+    }
+    rest.starts_with(|ch: char| ch.is_ascii_digit())
+        && rest
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || "/:.".contains(ch))
+}
+
+/// Taiwanese readings of the number in `raw`, in the output script first.
+/// The text as typed is always the last candidate.
+pub(crate) fn number_candidates(engine: &EngInner, raw: &str) -> Vec<Buffer> {
+    let readings = numerals::readings(raw);
+    let hanji = readings.iter().map(|r| r.hanji.clone());
+    let lomaji = readings.iter().map(|r| output_form(engine, &r.lomaji));
+
+    let mut values: Vec<String> = if engine.conf.is_hanji_first() {
+        hanji.chain(lomaji).collect()
+    } else {
+        lomaji.chain(hanji).collect()
+    };
+    values.push(raw.to_string());
+
+    let mut seen = HashSet::new();
+    values
+        .into_iter()
+        .filter(|value| seen.insert(value.clone()))
+        .map(|value| {
+            let elem = StringElem::from_raw_input(raw.to_string(), value);
+            let mut buffer: Buffer = BufferElementEnum::from(elem).into();
+            buffer.set_converted(true);
+            buffer
+        })
+        .collect()
+}
+
+/// The user's snippets for `raw`, in the order they were added
+pub(crate) fn snippet_candidates(engine: &EngInner, raw: &str) -> Vec<Buffer> {
+    engine
+        .snippets
+        .get(raw)
+        .iter()
+        .map(|value| {
+            let elem =
+                StringElem::from_raw_input(raw.to_string(), value.clone());
+            let mut buffer: Buffer = BufferElementEnum::from(elem).into();
+            buffer.set_converted(true);
+            buffer
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum Script {
+    Hanji,
+    HanLo,
+    Lomaji,
+}
+
+fn script_of(text: &str) -> Script {
+    let hanji = text.chars().any(|ch| ch.is_hanji());
+    let lomaji = text.chars().any(|ch| ch.is_alphabetic() && !ch.is_hanji());
+
+    match (hanji, lomaji) {
+        (true, false) => Script::Hanji,
+        (true, true) => Script::HanLo,
+        _ => Script::Lomaji,
+    }
+}
+
+/// Moves the preferred script ahead of other candidates of the same length.
+/// Only used when Hanji is the output mode.
+fn rank_by_script(conf: &Config, candidates: &mut [Buffer]) {
+    if !conf.is_hanji_first() {
+        return;
+    }
+
+    let preferred = match conf.script_preference() {
+        ScriptPreference::Any => return,
+        ScriptPreference::Hanji => Script::Hanji,
+        ScriptPreference::HanLo => Script::HanLo,
+    };
+
+    // Stable, so dictionary order is kept otherwise
+    candidates.sort_by_key(|buf| {
+        (
+            Reverse(buf.raw_text().len()),
+            script_of(&buf.display_text()) != preferred,
+        )
+    });
+}
+
+/// Leaves out variant characters and lomaji-only candidates as configured.
+/// A filter that would leave no candidates at all is skipped.
+fn filter_by_script(conf: &Config, candidates: &mut Vec<Buffer>) {
+    if conf.is_standard_only() {
+        retain_if_any(candidates, |buf| !buf.is_variant());
+    }
+
+    if conf.is_hanji_first() && conf.is_lomaji_hidden() {
+        retain_if_any(candidates, |buf| {
+            script_of(&buf.display_text()) != Script::Lomaji
+        });
+    }
+}
+
+fn retain_if_any<F>(candidates: &mut Vec<Buffer>, f: F)
+where
+    F: Fn(&Buffer) -> bool,
+{
+    if candidates.iter().any(&f) {
+        candidates.retain(f);
+    }
+}
+
+/// Candidates for words that start with `query`, leaving out any output
+/// already offered in `exact`
+fn predicted_candidates(
+    engine: &EngInner,
+    query: &str,
+    exact: &[Buffer],
+) -> Result<Vec<Buffer>> {
+    let EngInner { db, conf, .. } = &engine;
+    if query.len() < MIN_PREDICTION_LEN || !query.is_ascii() {
+        return Ok(Vec::new());
+    }
+
+    let query = query.to_ascii_lowercase();
+    let candidates = db.select_predictions(
+        conf.tone_mode().input_types(),
+        &query,
+        conf.prediction_limit(),
+    )?;
+
+    Ok(converted_candidates(engine, candidates, exact, |conv| {
+        conv.predicted = true;
+        let mut elem =
+            KhiinElem::from_conversion(&conv.key_sequence, conv).ok()?;
+        // The rest of the word has not been typed yet
+        elem.realign(&query);
+        Some(elem)
+    }))
+}
+
+/// Candidates for words whose syllables start with each letter of `query`,
+/// leaving out any output already offered in `exact`
+fn abbreviated_candidates(
+    engine: &EngInner,
+    query: &str,
+    exact: &[Buffer],
+) -> Result<Vec<Buffer>> {
+    let EngInner { db, .. } = &engine;
+    if query.len() < MIN_ABBREVIATION_LEN
+        || !query.chars().all(|ch| ch.is_ascii_alphabetic())
+    {
+        return Ok(Vec::new());
+    }
+
+    let query = query.to_ascii_lowercase();
+    let candidates = db.select_conversions_for_multiple(
+        &[InputType::Abbreviated],
+        &vec![query.as_str()],
+    )?;
+
+    Ok(converted_candidates(engine, candidates, exact, |conv| {
+        // The abbreviation cannot be aligned with the syllables, so the
+        // element is built from the full spelling
+        let keys: String = conv
+            .input
+            .split(' ')
+            .map(|syl| poj_syl_to_key_sequences(syl).2)
+            .collect();
+        let mut elem = KhiinElem::from_conversion(&keys, conv).ok()?;
+        elem.realign_abbreviation(&query);
+        Some(elem)
+    }))
+}
+
+/// Candidates for corrected spellings of the start of `query`, leaving out
+/// any output already offered in `exact`
+fn fuzzy_candidates(
+    engine: &EngInner,
+    query: &str,
+    exact: &[Buffer],
+) -> Result<Vec<Buffer>> {
+    let EngInner { db, dict, conf, .. } = &engine;
+    let corrections = find_corrections(dict, conf, query);
+    if corrections.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Longest typed prefix first, so the first entry for each key wins
+    let mut typed_lens = HashMap::new();
+    for correction in corrections.iter() {
+        typed_lens
+            .entry(correction.keys.as_str())
+            .or_insert(correction.typed_len);
+    }
+    let keys: Vec<&str> = corrections.iter().map(|c| c.keys.as_str()).collect();
+
+    let mut candidates = db.select_conversions_for_multiple(
+        conf.tone_mode().input_types(),
+        &keys,
+    )?;
+    candidates.retain(|c| typed_lens.contains_key(c.key_sequence.as_str()));
+
+    Ok(converted_candidates(engine, candidates, exact, |conv| {
+        let typed_len = typed_lens[conv.key_sequence.as_str()];
+        conv.fuzzy = true;
+        let mut elem =
+            KhiinElem::from_conversion(&conv.key_sequence, conv).ok()?;
+        elem.realign(&query[..typed_len]);
+        Some(elem)
+    }))
+}
+
+/// Converted candidates for `candidates`, leaving out any output already
+/// offered in `exact` or earlier in `candidates`. `build` makes the element
+/// for a conversion, aligned with what was typed, or `None` to skip it.
+fn converted_candidates<F>(
+    engine: &EngInner,
+    candidates: Vec<KeyConversion>,
+    exact: &[Buffer],
+    mut build: F,
+) -> Vec<Buffer>
+where
+    F: FnMut(&mut KeyConversion) -> Option<KhiinElem>,
+{
+    let mut seen: HashSet<String> =
+        exact.iter().map(|buf| buf.display_text()).collect();
+    let mut result = Vec::new();
+
+    for mut conv in candidates.into_iter() {
+        write_output_form(engine, &mut conv);
+        if !seen.insert(conv.output.clone()) {
+            continue;
+        }
+
+        if let Some(elem) = build(&mut conv) {
+            let mut buffer: Buffer = BufferElementEnum::from(elem).into();
+            buffer.set_converted(true);
+            result.push(buffer);
+        }
     }
+
+    result
 }
 
 fn candidates_for_splittable(
     engine: &EngInner,
     query: &str,
 ) -> Result<Vec<Buffer>> {
-    let EngInner { db, dict, conf } = &engine;
+    let EngInner { db, dict, conf, .. } = &engine;
     let mut words = dict.all_words_from_start(query);
     words.retain(|&w| {
         if let Some(rem) = query.strip_prefix(w) {
@@ -55,12 +347,17 @@ fn candidates_for_splittable(
         }
     });
 
-    let candidates =
-        db.select_conversions_for_multiple(conf.tone_mode().into(), &words)?;
+    let candidates = db.select_conversions_for_multiple(
+        conf.tone_mode().input_types(),
+        &words,
+    )?;
 
-    let result = candidates
+    let mut result: Vec<Buffer> = candidates
         .into_iter()
-        .map(|conv| KhiinElem::from_conversion(&conv.key_sequence, &conv))
+        .map(|mut conv| {
+            write_output_form(engine, &mut conv);
+            KhiinElem::from_conversion(&conv.key_sequence, &conv)
+        })
         .filter(|elem| elem.is_ok())
         .map(|elem| elem.unwrap().into())
         .filter(|elem: &BufferElementEnum| {
@@ -74,6 +371,9 @@ fn candidates_for_splittable(
         })
         .collect();
 
+    // Variant forms may write two outputs the same way
+    let mut seen = HashSet::new();
+    result.retain(|buf| seen.insert(buf.display_text()));
     Ok(result)
 }
 
@@ -98,7 +398,7 @@ pub(crate) fn get_candidates_for_word(
     engine: &EngInner,
     query: &str,
 ) -> Result<Vec<Buffer>> {
-    let EngInner { db, dict, conf } = &engine;
+    let EngInner { db, dict, conf, .. } = &engine;
     let raw_input = query.to_string().to_ascii_lowercase();
     let case_type = get_case_type(query);
     let candidates = db.select_conversions_for_tone(
@@ -117,6 +417,7 @@ pub(crate) fn get_candidates_for_word(
             } else if (conf.khin_mode() == KhinMode::Hyphen) {
                 conv.convert_to_khin_hyphen();
             }
+            write_output_form(engine, &mut conv);
             KhiinElem::from_conversion(&conv.key_sequence, &conv)
         })
         .filter(|elem| elem.is_ok())
@@ -133,6 +434,8 @@ pub(crate) fn get_candidates_for_word(
         .collect();
     let mut seen = HashSet::new();
     result.retain(|elem| seen.insert(elem.display_text().to_string()));
+    rank_by_script(conf, &mut result);
+    filter_by_script(conf, &mut result);
     Ok(result)
 }
 
@@ -141,7 +444,7 @@ pub(crate) fn get_candidates_for_word_with_tone(
     query: &str,
     tone_char: char,
 ) -> Result<Vec<Buffer>> {
-    let EngInner { db, dict, conf } = &engine;
+    let EngInner { db, dict, conf, .. } = &engine;
     let mut tone_key = get_numberic_tone_char(engine, tone_char);
     if (tone_char == engine.conf.t8() && get_shared_t8_tone(engine) != Tone::T8)
     {
@@ -184,6 +487,7 @@ pub(crate) fn get_candidates_for_word_with_tone(
             } else if (conf.khin_mode() == KhinMode::Hyphen) {
                 conv.convert_to_khin_hyphen();
             }
+            write_output_form(engine, &mut conv);
             KhiinElem::from_conversion(&conv.key_sequence, &conv)
         })
         .filter(|elem| elem.is_ok())
@@ -200,6 +504,8 @@ pub(crate) fn get_candidates_for_word_with_tone(
         .collect();
     let mut seen = HashSet::new();
     result.retain(|elem| seen.insert(elem.display_text().to_string()));
+    rank_by_script(conf, &mut result);
+    filter_by_script(conf, &mut result);
     Ok(result)
 }
 
@@ -393,17 +699,28 @@ fn convert_section(
 ) -> Result<Vec<BufferElementEnum>> {
     let mut ret = Vec::new();
 
+    let khin_mode = engine.conf.khin_mode();
+    let autokhin = engine.conf.is_autokhin() && khin_mode != KhinMode::Khinless;
+
     let words = engine.dict.segment(section)?;
-    for word in words {
+    for (i, word) in words.iter().enumerate() {
         let conversions = engine.db.select_conversions(
-            engine.conf.tone_mode().into(),
+            engine.conf.tone_mode().input_types(),
             word.as_str(),
-            Some(1),
+            if autokhin { None } else { Some(1) },
         )?;
 
         if let Some(conv) = conversions.get(0) {
+            let mut conv = conv.clone();
+            if autokhin
+                && apply_autokhin(&mut conv, &conversions, i > 0)
+                && khin_mode == KhinMode::Hyphen
+            {
+                conv.convert_to_khin_hyphen();
+            }
+            write_output_form(engine, &mut conv);
             let khiin_elem: KhiinElem =
-                KhiinElem::from_conversion(&word, conv)?;
+                KhiinElem::from_conversion(word, &conv)?;
             ret.push(khiin_elem.into());
         }
     }
@@ -411,6 +728,61 @@ fn convert_section(
     Ok(ret)
 }
 
+/// Writes the output of `conv` in the user's preferred script and Hanji
+/// forms. Case and khin marks are applied to the lomaji before this.
+fn write_output_form(engine: &EngInner, conv: &mut KeyConversion) {
+    conv.output = output_form(engine, &conv.output);
+}
+
+fn output_form(engine: &EngInner, text: &str) -> String {
+    let mut text = text.to_string();
+    if engine.conf.hanji_variant() == HanjiVariant::Common {
+        text = engine.variants.rewrite(&text);
+    }
+    if engine.conf.is_tps_output() {
+        text = lomaji_to_tps(&text);
+    } else if engine.conf.is_ascii_output() {
+        text = lomaji_to_ascii(
+            &text,
+            engine.conf.ascii_spelling(),
+            engine.conf.ascii_khin(),
+        );
+    }
+    text
+}
+
+/// Replaces `conv` with a khin form of the same output from `conversions`,
+/// if the dictionary has one. A form starting with a khin syllable is only
+/// used after another word, and only if it is at least as common as `conv`.
+/// Returns whether `conv` was replaced.
+fn apply_autokhin(
+    conv: &mut KeyConversion,
+    conversions: &[KeyConversion],
+    follows_word: bool,
+) -> bool {
+    let khin_form = conversions
+        .iter()
+        .filter(|other| {
+            other.khin_ok
+                && other.input_id == conv.input_id
+                && other.output.contains('·')
+                && other.output.replace('·', "") == conv.output
+        })
+        .filter(|other| {
+            !other.output.starts_with('·')
+                || (follows_word && other.weight >= conv.weight)
+        })
+        .max_by_key(|other| other.weight);
+
+    match khin_form {
+        Some(khin_form) => {
+            *conv = khin_form.clone();
+            true
+        },
+        None => false,
+    }
+}
+
 fn convert_section_by_hanlo(
     engine: &EngInner,
     ty: SectionType,
@@ -421,15 +793,24 @@ fn convert_section_by_hanlo(
     let mut ret = Vec::new();
     let khin_mode = engine.conf.khin_mode();
 
+    let autokhin = engine.conf.is_autokhin() && khin_mode != KhinMode::Khinless;
+
     let words = engine.dict.segment(section)?;
-    for word in words {
+    for (i, word) in words.into_iter().enumerate() {
         let mut conversions = engine.db.select_conversions_by_hanlo(
-            engine.conf.tone_mode().into(),
+            engine.conf.tone_mode().input_types(),
             word.as_str(),
             is_hanji_first,
             engine.conf.is_khinless(),
+            if autokhin { None } else { Some(1) },
         )?;
 
+        if autokhin && !conversions.is_empty() {
+            let mut conv = conversions[0].clone();
+            apply_autokhin(&mut conv, &conversions, i > 0);
+            conversions[0] = conv;
+        }
+
         if let Some(conv) = conversions.get_mut(0) {
             conv.set_output_case_type(case_type.clone());
             conv.mark_guess_annotation();
@@ -438,6 +819,7 @@ fn convert_section_by_hanlo(
             } else if (khin_mode == KhinMode::Hyphen) {
                 conv.convert_to_khin_hyphen();
             }
+            write_output_form(engine, conv);
             let khiin_elem: KhiinElem =
                 KhiinElem::from_conversion(&word, conv)?;
             ret.push(khiin_elem.into());
@@ -451,7 +833,7 @@ fn convert_section_by_hanlo(
 }
 
 pub(crate) fn get_numberic_tone_char(engine: &EngInner, ch: char) -> char {
-    if (engine.conf.tone_mode() == ToneMode::Telex) {
+    if (engine.conf.tone_mode() != ToneMode::Numeric) {
         let tone = char_to_tone(engine, ch);
         match tone {
             Tone::None => ch,
@@ -470,6 +852,39 @@ pub(crate) fn get_numberic_tone_char(engine: &EngInner, ch: char) -> char {
     }
 }
 
+/// The keys to type `lomaji` in the current tone mode and key configuration.
+/// Hyphens are left out, since syllables are split as they are typed, and
+/// khin syllables start with the khin key.
+pub(crate) fn lomaji_to_keys(engine: &EngInner, lomaji: &str) -> String {
+    let mut keys = String::new();
+
+    for word in lomaji.split_whitespace() {
+        let mut khin = false;
+        for (i, syl) in word.split('-').enumerate() {
+            if syl.is_empty() {
+                khin = i > 0;
+                continue;
+            }
+
+            let mut syl = syl.to_string();
+            khin |= strip_khin(&mut syl);
+            if khin && engine.conf.khin_mode() != KhinMode::Khinless {
+                keys.push(engine.conf.khin());
+            }
+            khin = false;
+
+            let (_, _, letters) = poj_syl_to_key_sequences(&syl);
+            keys.push_str(&letters.to_lowercase());
+            let tone_key = tone_to_char(engine, &strip_tone_diacritic(&syl).1);
+            if tone_key != ' ' {
+                keys.push(tone_key);
+            }
+        }
+    }
+
+    keys
+}
+
 fn tone_to_char(engine: &EngInner, tone: &Tone) -> char {
     match tone {
         Tone::None => ' ',
@@ -486,7 +901,9 @@ fn tone_to_char(engine: &EngInner, tone: &Tone) -> char {
 }
 
 fn char_to_tone(engine: &EngInner, ch: char) -> Tone {
-    if ch == engine.conf.t2() {
+    if engine.conf.is_fallback_tone_digit(ch) {
+        key_to_tone(ch)
+    } else if ch == engine.conf.t2() {
         Tone::T2
     } else if ch == engine.conf.t3() {
         Tone::T3
@@ -525,7 +942,10 @@ fn get_shared_t8_tone(engine: &EngInner) -> Tone {
 
 #[cfg(test)]
 mod tests {
+    use khiin_ji::ascii::Spelling;
+
     use super::*;
+    use crate::data::HanjiVariants;
     use crate::tests::*;
 
     fn setup() -> (Database, Dictionary, Config) {
@@ -539,6 +959,115 @@ mod tests {
         log::debug!("{:#?}", comp);
     }
 
+    #[test]
+    fn it_applies_autokhin() -> Result<()> {
+        let (mut engine, _) = test_harness();
+        engine.conf.set_tone_mode(ToneMode::Numeric);
+        let guess = |engine: &EngInner, raw: &str| -> Result<String> {
+            let mut buf = convert_guess(engine, raw)?;
+            buf.set_converted(true);
+            Ok(buf.display_text())
+        };
+        assert_eq!(guess(&engine, "tng2lai5")?, "tńg lâi");
+
+        engine.conf.set_autokhin(true);
+        assert_eq!(guess(&engine, "tng2lai5")?, "tńg--lâi");
+        engine.conf.set_khin_mode(KhinMode::Dot);
+        assert_eq!(guess(&engine, "tng2lai5")?, "tńg ·lâi");
+        engine.conf.set_khin_mode(KhinMode::Khinless);
+        assert_eq!(guess(&engine, "tng2lai5")?, "tńg lâi");
+        Ok(())
+    }
+
+    #[test]
+    fn it_filters_candidates_by_script() -> Result<()> {
+        let (mut engine, _) = test_harness();
+        engine.conf.set_output_mode(OutputMode::Hanji);
+        let is_lomaji =
+            |buf: &Buffer| script_of(&buf.display_text()) == Script::Lomaji;
+
+        let cands = get_candidates(&engine, "a")?;
+        assert!(cands.iter().any(is_lomaji));
+
+        engine.conf.set_script_preference(ScriptPreference::Hanji);
+        let cands = get_candidates(&engine, "a")?;
+        let first_lomaji = cands.iter().position(is_lomaji).unwrap();
+        assert!(cands[first_lomaji..]
+            .iter()
+            .filter(|buf| buf.raw_text().len() == 1)
+            .all(is_lomaji));
+
+        engine.conf.set_lomaji_hidden(true);
+        let cands = get_candidates(&engine, "a")?;
+        assert!(!cands.is_empty());
+        assert!(!cands.iter().any(is_lomaji));
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_preferred_hanji_variants() -> Result<()> {
+        let (mut engine, _) = test_harness();
+        engine.conf.set_tone_mode(ToneMode::Numeric);
+        engine.variants =
+            HanjiVariants::from_pairs([("食".to_string(), "呷".to_string())]);
+        let texts = |engine: &EngInner| -> Result<Vec<String>> {
+            let cands = get_candidates(engine, "chiah8png7")?;
+            Ok(cands.iter().map(|c| c.display_text()).collect())
+        };
+
+        assert!(texts(&engine)?.contains(&"食飯".to_string()));
+        engine.conf.set_hanji_variant(HanjiVariant::Common);
+        let cands = texts(&engine)?;
+        assert!(cands.contains(&"呷飯".to_string()));
+        assert!(!cands.iter().any(|c| c.contains('食')));
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_phonetic_symbols() -> Result<()> {
+        let (mut engine, _) = test_harness();
+        engine.conf.set_tone_mode(ToneMode::Numeric);
+        engine.conf.set_output_mode(OutputMode::Tps);
+        let cands = get_candidates(&engine, "chiah8png7")?;
+        let texts: Vec<_> = cands.iter().map(|c| c.display_text()).collect();
+
+        assert!(texts.contains(&"ㄐㄧㄚㆷ˙ ㄅㆭ˫".to_string()));
+        assert!(texts.contains(&"食飯".to_string()));
+        assert!(!texts.iter().any(|t| t.contains("chia̍h")));
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_ascii_lomaji() -> Result<()> {
+        let (mut engine, _) = test_harness();
+        engine.conf.set_tone_mode(ToneMode::Numeric);
+        engine.conf.set_output_mode(OutputMode::Ascii);
+        let texts = |engine: &EngInner| -> Result<Vec<String>> {
+            let cands = get_candidates(engine, "chhoa7")?;
+            Ok(cands.iter().map(|c| c.display_text()).collect())
+        };
+
+        assert!(texts(&engine)?.contains(&"chhoa7".to_string()));
+        engine.conf.set_ascii_spelling(Spelling::TaiLo);
+        assert!(texts(&engine)?.contains(&"tshua7".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn it_hyphenates_khin_before_writing_ascii() -> Result<()> {
+        let (mut engine, _) = test_harness();
+        engine.conf.set_khin_mode(KhinMode::Hyphen);
+        engine.conf.set_output_mode(OutputMode::Ascii);
+
+        let texts: Vec<_> = get_candidates_for_word(&engine, "tnglai")?
+            .iter()
+            .map(|c| c.display_text())
+            .collect();
+        assert!(texts.contains(&"tng2--lai5".to_string()));
+        assert!(!texts.contains(&"tng2 --lai5".to_string()));
+        Ok(())
+    }
+
     #[test]
     fn it_gets_candidates() -> Result<()> {
         let (engine, _) = test_harness();
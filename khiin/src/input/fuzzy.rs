@@ -0,0 +1,269 @@
+//! Tolerant matching for common typing mistakes.
+//!
+//! Two kinds of corrections are tried for each prefix of the input: spellings
+//! from a set of known confusions (e.g. `ts` typed for `ch`), and dictionary
+//! words within one edit. They are offered after the exact matches, as
+//! fallback candidates.
+
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::data::Dictionary;
+
+/// Shorter prefixes are too ambiguous to search by edit distance
+const MIN_EDIT_LEN: usize = 3;
+const MAX_EDITS: usize = 1;
+/// Confusions applied together to the same prefix
+const MAX_CONFUSIONS: usize = 2;
+const MAX_CORRECTIONS: usize = 20;
+
+const DEFAULT_CONFUSIONS: [&str; 8] = [
+    "o/oo",
+    "o/ou",
+    "e/ee",
+    "n/nn",
+    "ts/ch",
+    "ing/eng",
+    "ik/ek",
+    "checked-h",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Confusion {
+    /// Either spelling may be typed for the other
+    Swap(String, String),
+    /// A checked syllable (tone 4 or 8) typed without its final `h`
+    CheckedH,
+}
+
+impl Confusion {
+    /// Parses a pair of spellings such as `ts/ch`, or `checked-h`
+    pub fn parse(value: &str) -> Result<Self> {
+        if value == "checked-h" {
+            return Ok(Confusion::CheckedH);
+        }
+
+        match value.split_once('/') {
+            Some((a, b)) if !a.is_empty() && !b.is_empty() && a != b => {
+                Ok(Confusion::Swap(a.to_string(), b.to_string()))
+            },
+            _ => Err(anyhow!("Invalid confusion: {}", value)),
+        }
+    }
+
+    fn apply(&self, keys: &str, checked_keys: &[char], out: &mut Vec<String>) {
+        match self {
+            Confusion::Swap(a, b) => {
+                replace_each(keys, a, b, out);
+                replace_each(keys, b, a, out);
+            },
+            Confusion::CheckedH => {
+                let mut prev = None;
+                for (i, ch) in keys.char_indices() {
+                    let after_vowel =
+                        prev.is_some_and(|p| "aeioun".contains(p));
+                    if after_vowel && checked_keys.contains(&ch) {
+                        let mut fixed = keys.to_string();
+                        fixed.insert(i, 'h');
+                        out.push(fixed);
+                    }
+                    prev = Some(ch);
+                }
+            },
+        }
+    }
+}
+
+fn replace_each(keys: &str, from: &str, to: &str, out: &mut Vec<String>) {
+    for (i, _) in keys.match_indices(from) {
+        let mut fixed = String::with_capacity(keys.len() + to.len());
+        fixed.push_str(&keys[..i]);
+        fixed.push_str(to);
+        fixed.push_str(&keys[i + from.len()..]);
+        out.push(fixed);
+    }
+}
+
+pub(crate) fn default_confusions() -> Vec<Confusion> {
+    DEFAULT_CONFUSIONS
+        .iter()
+        .filter_map(|c| Confusion::parse(c).ok())
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Correction {
+    /// Length in bytes of the typed prefix that was corrected
+    pub typed_len: usize,
+    /// The key sequence found in the dictionary
+    pub keys: String,
+}
+
+/// Corrections for prefixes of `query`, longest prefix first. Only prefixes
+/// followed by a segmentable remainder are considered.
+pub(crate) fn find_corrections(
+    dict: &Dictionary,
+    conf: &Config,
+    query: &str,
+) -> Vec<Correction> {
+    let checked_keys = ['4', '8', conf.t8()];
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+
+    let mut push = |typed_len: usize, keys: String| {
+        if dict.can_segment(&query[typed_len..])
+            && seen.insert((typed_len, keys.clone()))
+        {
+            result.push(Correction { typed_len, keys });
+        }
+    };
+
+    for len in 1..=query.len() {
+        if !query.is_char_boundary(len) {
+            continue;
+        }
+        let typed = &query[..len];
+        for keys in confusion_variants(typed, conf.confusions(), &checked_keys)
+        {
+            if dict.contains_word(&keys) {
+                push(len, keys);
+            }
+        }
+    }
+
+    if query.len() >= MIN_EDIT_LEN {
+        for (len, keys) in dict.find_words_near(query, MAX_EDITS) {
+            let typed = &query[..len];
+            if len >= MIN_EDIT_LEN
+                && keys != typed
+                && !dict.contains_word(typed)
+            {
+                push(len, keys);
+            }
+        }
+    }
+
+    result.sort_by_key(|c| std::cmp::Reverse(c.typed_len));
+    result.truncate(MAX_CORRECTIONS);
+    result
+}
+
+fn confusion_variants(
+    typed: &str,
+    confusions: &[Confusion],
+    checked_keys: &[char],
+) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut seen = HashSet::new();
+    let mut frontier = vec![typed.to_string()];
+
+    for _ in 0..MAX_CONFUSIONS {
+        let mut next = Vec::new();
+        for keys in frontier.iter() {
+            for confusion in confusions.iter() {
+                confusion.apply(keys, checked_keys, &mut next);
+            }
+        }
+        next.retain(|keys| keys != typed && seen.insert(keys.clone()));
+        found.extend(next.iter().cloned());
+        frontier = next;
+    }
+
+    found
+}
+
+/// Maps each byte position of `corrected` to a byte position of `typed`,
+/// following the cheapest edit between them. Extra typed characters go to
+/// the left of a boundary.
+pub(crate) fn align(typed: &str, corrected: &str) -> Vec<usize> {
+    let (t, c) = (typed.as_bytes(), corrected.as_bytes());
+    let mut d = vec![vec![0; t.len() + 1]; c.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=c.len() {
+        for j in 1..=t.len() {
+            let substitution =
+                d[i - 1][j - 1] + (c[i - 1] != t[j - 1]) as usize;
+            let insertion = d[i - 1][j] + 1;
+            let deletion = d[i][j - 1] + 1;
+            d[i][j] = substitution.min(insertion).min(deletion);
+        }
+    }
+
+    let mut map = vec![0; c.len() + 1];
+    let (mut i, mut j) = (c.len(), t.len());
+    map[i] = j;
+    while i > 0 {
+        if j > 0 && d[i][j] == d[i - 1][j - 1] + (c[i - 1] != t[j - 1]) as usize
+        {
+            i -= 1;
+            j -= 1;
+        } else if d[i][j] == d[i - 1][j] + 1 {
+            i -= 1;
+        } else {
+            j -= 1;
+            continue;
+        }
+        map[i] = j;
+    }
+    map[0] = 0;
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::*;
+
+    #[test]
+    fn it_parses_confusions() {
+        assert_eq!(
+            Confusion::parse("ts/ch").unwrap(),
+            Confusion::Swap("ts".into(), "ch".into())
+        );
+        assert_eq!(Confusion::parse("checked-h").unwrap(), Confusion::CheckedH);
+        assert!(Confusion::parse("ts").is_err());
+        assert!(Confusion::parse("/ch").is_err());
+        assert_eq!(default_confusions().len(), DEFAULT_CONFUSIONS.len());
+    }
+
+    #[test]
+    fn it_applies_confusions() {
+        let confusions = default_confusions();
+        let variants = confusion_variants("tsia8", &confusions, &['4', '8']);
+        assert!(variants.contains(&"chiah8".to_string()));
+        assert!(variants.contains(&"tsiah8".to_string()));
+        assert!(!variants.contains(&"tsia8".to_string()));
+    }
+
+    #[test]
+    fn it_aligns_corrections() {
+        assert_eq!(align("tsiah", "chiah"), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(align("chia8", "chiah8"), vec![0, 1, 2, 3, 4, 4, 5]);
+        assert_eq!(align("hoo", "ho"), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn it_finds_corrections() {
+        let dict = get_dict();
+        let conf = get_conf();
+        let found = find_corrections(&dict, &conf, "tsiah8");
+        assert_eq!(
+            found[0],
+            Correction {
+                typed_len: 6,
+                keys: "chiah8".into()
+            }
+        );
+        let found = find_corrections(&dict, &conf, "chia8");
+        assert!(found.iter().any(|c| c.keys == "chiah8"));
+    }
+}
@@ -1,13 +1,14 @@
 use std::default;
 
 use regex::Regex;
-use unicode_normalization::UnicodeNormalization;
 
 use khiin_ji::lomaji::get_tone_position;
 use khiin_ji::lomaji::key_to_tone;
 use khiin_ji::lomaji::strip_khin;
 use khiin_ji::lomaji::strip_tone_diacritic;
 use khiin_ji::lomaji::tone_to_char;
+use khiin_ji::normalize::normalize;
+use khiin_ji::normalize::Normalization;
 use khiin_ji::Tone;
 
 #[derive(Default, Debug, PartialEq, Clone)]
@@ -251,10 +252,7 @@ impl Syllable {
 ///   * for the T8 `o̤̍`, which has no precomposed letter+tone form, the tone
 ///     mark precedes the diaeresis below: `o` U+030D U+0324
 fn to_khiin_encoding(s: &str) -> String {
-    let nfc: String = s.nfc().collect();
-    // NFC orders the diaeresis below (ccc 220) before the vertical line above
-    // (ccc 230), but the dictionary stores the tone mark first; swap to match.
-    nfc.replace("\u{0324}\u{030d}", "\u{030d}\u{0324}")
+    normalize(s, Normalization::FontSafe)
 }
 
 fn get_tone_char(tone: Tone) -> Option<char> {
@@ -0,0 +1,263 @@
+use khiin_ji::Tone;
+
+use crate::config::Config;
+use crate::config::ToneMode;
+
+/// A key on the Taiwanese Phonetic Symbols layout
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TpsKey {
+    Symbol(char),
+    /// Nasalizes the vowel before it, like ⁿ
+    Nasal,
+    /// Ends a syllable. Space ends it with tone 1 or 4.
+    ToneMark(Tone),
+}
+
+/// Based on the common Mandarin zhuyin layout, with the Taiwanese symbols
+/// on the keys of Mandarin-only ones
+fn key_to_tps(key: char) -> Option<TpsKey> {
+    use TpsKey::*;
+
+    let key = match key {
+        '1' => Symbol('ㄅ'),
+        'q' => Symbol('ㄆ'),
+        'a' => Symbol('ㄇ'),
+        'z' => Symbol('ㆠ'),
+        '2' => Symbol('ㄉ'),
+        'w' => Symbol('ㄊ'),
+        's' => Symbol('ㄋ'),
+        'x' => Symbol('ㄌ'),
+        'e' => Symbol('ㄍ'),
+        'd' => Symbol('ㄎ'),
+        'c' => Symbol('ㄏ'),
+        'r' => Symbol('ㄐ'),
+        'f' => Symbol('ㄑ'),
+        'v' => Symbol('ㄒ'),
+        '5' => Symbol('ㆣ'),
+        't' => Symbol('ㄫ'),
+        'g' => Symbol('ㆢ'),
+        'b' => Symbol('ㆡ'),
+        'y' => Symbol('ㄗ'),
+        'h' => Symbol('ㄘ'),
+        'n' => Symbol('ㄙ'),
+        'u' => Symbol('ㄧ'),
+        'j' => Symbol('ㄨ'),
+        'm' => Symbol('ㆦ'),
+        '8' => Symbol('ㄚ'),
+        'i' => Symbol('ㆤ'),
+        'k' => Symbol('ㄜ'),
+        '9' => Symbol('ㄞ'),
+        'l' => Symbol('ㄠ'),
+        '0' => Symbol('ㄢ'),
+        'p' => Symbol('ㄣ'),
+        ';' => Symbol('ㄤ'),
+        '/' => Symbol('ㄥ'),
+        'o' => Symbol('ㆬ'),
+        ',' => Symbol('ㆭ'),
+        '.' => Symbol('ㆲ'),
+        '-' => Nasal,
+        ' ' => ToneMark(Tone::T1),
+        '4' => ToneMark(Tone::T2),
+        '3' => ToneMark(Tone::T3),
+        '6' => ToneMark(Tone::T5),
+        '[' => ToneMark(Tone::T7),
+        '7' => ToneMark(Tone::T8),
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Lomaji keys for `sym`, which may depend on the symbol after it
+fn spell(sym: char, next: Option<char>) -> &'static str {
+    let next_is = |syms: &str| next.is_some_and(|n| syms.contains(n));
+
+    match sym {
+        'ㄅ' | 'ㆴ' => "p",
+        'ㄆ' => "ph",
+        'ㆠ' => "b",
+        'ㄇ' | 'ㆬ' => "m",
+        'ㄉ' | 'ㆵ' => "t",
+        'ㄊ' => "th",
+        'ㄋ' | 'ㄣ' => "n",
+        'ㄌ' => "l",
+        'ㄍ' | 'ㆶ' => "k",
+        'ㄎ' => "kh",
+        'ㆣ' => "g",
+        'ㄫ' | 'ㄥ' | 'ㆭ' => "ng",
+        'ㄏ' | 'ㆷ' => "h",
+        'ㄗ' | 'ㄐ' => "ch",
+        'ㄘ' | 'ㄑ' => "chh",
+        'ㄙ' | 'ㄒ' => "s",
+        'ㆡ' | 'ㆢ' => "j",
+        // POJ writes oa and oe
+        'ㄨ' if next_is("ㄚㆤㄞㄢ") => "o",
+        'ㄨ' => "u",
+        // POJ writes eng and ek
+        'ㄧ' if next_is("ㄥㄫㆶㄍ") => "e",
+        'ㄧ' => "i",
+        // POJ writes ong, ok, om and op
+        'ㆦ' if next_is("ㄥㄫㆶㄍㆬㄇㆴㄅ") => "o",
+        'ㆦ' => "oo",
+        'ㄚ' => "a",
+        'ㆤ' => "e",
+        'ㄜ' => "o",
+        'ㄞ' => "ai",
+        'ㄠ' => "au",
+        'ㄢ' => "an",
+        'ㄤ' => "ang",
+        'ㆰ' => "am",
+        'ㆱ' => "om",
+        'ㆲ' => "ong",
+        _ => "",
+    }
+}
+
+/// Turns keys typed on a Taiwanese Phonetic Symbols layout into the lomaji
+/// keys of the current tone mode, one syllable at a time, so that they go
+/// through the same conversion as typed lomaji.
+///
+/// Symbols whose spelling depends on the next one are held back until it is
+/// typed. Every syllable ends with a tone key, so the lomaji is never
+/// ambiguous.
+#[derive(Debug, Default)]
+pub(crate) struct TpsKeyboard {
+    held: Option<char>,
+    last: Option<char>,
+}
+
+impl TpsKeyboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.held = None;
+        self.last = None;
+    }
+
+    /// Whether a syllable has been started
+    pub fn is_composing(&self) -> bool {
+        self.last.is_some()
+    }
+
+    /// Drops a held symbol, if there is one. Returns false if the key should
+    /// be handled by the buffer instead.
+    pub fn pop(&mut self) -> bool {
+        if self.held.take().is_some() {
+            true
+        } else {
+            self.reset();
+            false
+        }
+    }
+
+    /// The lomaji keys to insert for `key`, or `None` if it is not on the
+    /// layout
+    pub fn on_key(&mut self, conf: &Config, key: char) -> Option<String> {
+        let mut keys = String::new();
+
+        match key_to_tps(key)? {
+            TpsKey::Symbol(sym) => {
+                if let Some(held) = self.held.take() {
+                    keys.push_str(spell(held, Some(sym)));
+                }
+                if "ㄨㄧㆦ".contains(sym) {
+                    self.held = Some(sym);
+                } else {
+                    keys.push_str(spell(sym, None));
+                }
+                self.last = Some(sym);
+            },
+            TpsKey::Nasal => {
+                if let Some(held) = self.held.take() {
+                    keys.push_str(spell(held, None));
+                }
+                keys.push_str("nn");
+            },
+            TpsKey::ToneMark(tone) => {
+                if !self.is_composing() {
+                    return None;
+                }
+                if let Some(held) = self.held.take() {
+                    keys.push_str(spell(held, None));
+                }
+                keys.extend(tone_key(conf, tone, self.last));
+                self.reset();
+            },
+        }
+
+        Some(keys)
+    }
+}
+
+/// Telex leaves tones 1 and 4 unmarked, so they have no key
+fn tone_key(conf: &Config, tone: Tone, last: Option<char>) -> Option<char> {
+    let key = match tone {
+        Tone::T2 => conf.t2(),
+        Tone::T3 => conf.t3(),
+        Tone::T5 => conf.t5(),
+        Tone::T7 => conf.t7(),
+        Tone::T8 => conf.t8(),
+        _ if conf.tone_mode() == ToneMode::Telex => return None,
+        _ if last.is_some_and(|l| "ㄅㄉㄍㄏㆴㆵㆶㆷ".contains(l)) => {
+            '4'
+        },
+        _ => '1',
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_keys(conf: &Config, keys: &str) -> String {
+        let mut kb = TpsKeyboard::new();
+        keys.chars().filter_map(|k| kb.on_key(conf, k)).collect()
+    }
+
+    #[test]
+    fn it_spells_syllables_as_lomaji_keys() {
+        let mut conf = Config::new();
+        conf.set_tone_mode(crate::config::ToneMode::Numeric);
+        assert_eq!(type_keys(&conf, "ru8c7"), "chiah8");
+        assert_eq!(type_keys(&conf, "1,["), "png7");
+        assert_eq!(type_keys(&conf, "5j84"), "goa2");
+        assert_eq!(type_keys(&conf, "du/ "), "kheng1");
+        assert_eq!(type_keys(&conf, "eu8- "), "kiann1");
+        assert_eq!(type_keys(&conf, "cm4"), "hoo2");
+        assert_eq!(type_keys(&conf, "e8c "), "kah4");
+        assert_eq!(type_keys(&conf, "2u. "), "tiong1");
+    }
+
+    #[test]
+    fn it_uses_the_telex_tone_keys() {
+        let conf = Config::new();
+        assert_eq!(type_keys(&conf, "cm4"), "hoos");
+        assert_eq!(type_keys(&conf, "x96"), "lail");
+        assert_eq!(type_keys(&conf, "eu8- "), "kiann");
+        assert_eq!(type_keys(&conf, "e8c "), "kah");
+    }
+
+    #[test]
+    fn it_keeps_digit_tones_in_hybrid_mode() {
+        let mut conf = Config::new();
+        conf.set_tone_mode(ToneMode::Hybrid);
+        assert_eq!(type_keys(&conf, "e8c "), "kah4");
+        assert_eq!(type_keys(&conf, "cm4"), "hoos");
+    }
+
+    #[test]
+    fn it_holds_back_symbols_until_the_next_one() {
+        let conf = Config::new();
+        let mut kb = TpsKeyboard::new();
+        assert_eq!(kb.on_key(&conf, 'e').unwrap(), "k");
+        assert_eq!(kb.on_key(&conf, 'j').unwrap(), "");
+        assert!(kb.pop());
+        assert_eq!(kb.on_key(&conf, 'j').unwrap(), "");
+        assert_eq!(kb.on_key(&conf, '8').unwrap(), "oa");
+        assert!(!kb.pop());
+        assert!(kb.on_key(&conf, ' ').is_none());
+    }
+}
@@ -3,8 +3,11 @@
 
 pub mod db;
 pub mod engine;
+pub mod spell;
 
+pub use crate::data::snapshot::Snapshot;
 pub use crate::engine::Engine;
+pub use crate::spell::SpellChecker;
 
 pub(crate) mod buffer;
 pub(crate) mod config;
@@ -0,0 +1,534 @@
+//! Proofreading of POJ and Tâi-lô text against the syllable list and the
+//! dictionary
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use anyhow::Result;
+use unicode_normalization::UnicodeNormalization;
+
+use khiin_ji::lomaji::get_tone_position;
+use khiin_ji::lomaji::is_legal_lomaji;
+use khiin_ji::lomaji::strip_tone_diacritic;
+use khiin_ji::lomaji::syllable_to_key_sequences;
+use khiin_ji::lomaji::tone_to_char;
+use khiin_ji::normalize::normalize;
+use khiin_ji::normalize::Normalization;
+use khiin_ji::unicode::*;
+use khiin_ji::Tone;
+
+use crate::data::trie::prefix_distances;
+use crate::data::SyllableTrie;
+use crate::db::Database;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// Not a syllable of the language
+    IllegalSyllable,
+    /// A tone mark on the wrong letter
+    MisplacedTone,
+    /// Not in the dictionary
+    UnknownWord,
+    /// Syllables joined differently from the dictionary
+    Hyphenation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// Offset of the text in question, in chars
+    pub start: usize,
+    /// End of the text in question, in chars
+    pub end: usize,
+    pub text: String,
+    pub suggestion: Option<String>,
+}
+
+/// Checks lomaji text for illegal syllables, misplaced tone marks, unknown
+/// words and hyphenation. Tâi-lô is read as POJ, so words and suggestions
+/// are spelled in POJ.
+pub struct SpellChecker {
+    syllable_trie: SyllableTrie,
+    /// Toneless key sequence and spelling of each syllable
+    syllables: Vec<(String, &'static str)>,
+    /// Lowercase dictionary spellings
+    words: HashSet<String>,
+    /// Dictionary spellings by their letters, without separators
+    by_letters: HashMap<String, String>,
+    /// Dictionary spellings by their letters, without tones or separators
+    by_toneless: HashMap<String, String>,
+}
+
+impl SpellChecker {
+    pub fn new(db: &Database) -> Result<Self> {
+        let syllables = khiin_data::SYLLABLES_TXT
+            .lines()
+            .filter_map(|syl| {
+                let key = syllable_to_key_sequences(syl).into_iter().next()?;
+                Some((key.to_lowercase(), syl))
+            })
+            .collect();
+
+        let mut words = HashSet::new();
+        let mut by_letters = HashMap::new();
+        let mut by_toneless = HashMap::new();
+        for word in db.select_lomaji_words()? {
+            let lower = word.to_lowercase();
+            by_letters
+                .entry(letters(&lower))
+                .or_insert_with(|| word.clone());
+            by_toneless
+                .entry(toneless(&lower))
+                .or_insert_with(|| word.clone());
+            words.insert(lower);
+        }
+
+        Ok(Self {
+            syllable_trie: SyllableTrie::new(),
+            syllables,
+            words,
+            by_letters,
+            by_toneless,
+        })
+    }
+
+    /// Issues in `text`, in order. Words are only looked up once all of
+    /// their syllables are correct.
+    pub fn check(&self, text: &str) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let tokens = tokenize(text);
+        let words: Vec<_> = tokens
+            .iter()
+            .map(|token| self.check_syllables(token, &mut issues))
+            .collect();
+
+        let mut i = 0;
+        while i < tokens.len() {
+            let Some(word) = &words[i] else {
+                i += 1;
+                continue;
+            };
+
+            if let (Some(next), Some(Some(next_word))) =
+                (tokens.get(i + 1), words.get(i + 1))
+            {
+                let hyphenated = format!("{}-{}", word, next_word);
+                let spaced = format!("{} {}", word, next_word);
+                if next.after_space
+                    && self.is_word(&hyphenated)
+                    && !self.is_word(&spaced)
+                {
+                    issues.push(Issue {
+                        kind: IssueKind::Hyphenation,
+                        start: tokens[i].start,
+                        end: next.end,
+                        text: format!("{} {}", tokens[i].text, next.text),
+                        suggestion: Some(format!(
+                            "{}-{}",
+                            tokens[i].text, next.text
+                        )),
+                    });
+                    i += 2;
+                    continue;
+                }
+            }
+
+            if !self.is_word(word) {
+                issues.push(self.unknown_word(&tokens[i], word));
+            }
+            i += 1;
+        }
+
+        issues.sort_by_key(|issue| issue.start);
+        issues
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        self.words.contains(&word.to_lowercase())
+    }
+
+    fn unknown_word(&self, token: &Token, word: &str) -> Issue {
+        let lower = word.to_lowercase();
+        let (kind, suggestion) =
+            if let Some(found) = self.by_letters.get(&letters(&lower)) {
+                (IssueKind::Hyphenation, Some(found))
+            } else {
+                let found = self.by_toneless.get(&toneless(&lower));
+                (IssueKind::UnknownWord, found)
+            };
+
+        Issue {
+            kind,
+            start: token.start,
+            end: token.end,
+            text: token.text.clone(),
+            suggestion: suggestion.map(|s| match_case(s, &token.text)),
+        }
+    }
+
+    /// Checks each syllable of `token`, returning its POJ spelling if they
+    /// are all correct
+    fn check_syllables(
+        &self,
+        token: &Token,
+        issues: &mut Vec<Issue>,
+    ) -> Option<String> {
+        let mut poj = String::new();
+        let mut ok = true;
+
+        for syl in token.syllables() {
+            if syl.hyphens > 2 {
+                let start = token.start + syl.offset - syl.hyphens;
+                issues.push(Issue {
+                    kind: IssueKind::Hyphenation,
+                    start,
+                    end: start + syl.hyphens,
+                    text: "-".repeat(syl.hyphens),
+                    suggestion: Some("--".to_string()),
+                });
+                ok = false;
+            }
+
+            let mut body = syl.text.as_str();
+            let mut start = token.start + syl.offset;
+            let dotted = body.starts_with(DOT_KHIN);
+            if dotted {
+                body = &body[DOT_KHIN.len_utf8()..];
+                start += 1;
+            }
+
+            if syl.hyphens > 0 {
+                poj.push('-');
+            }
+            if dotted || syl.hyphens >= 2 {
+                poj.push(DOT_KHIN);
+            }
+
+            if body.is_empty() {
+                continue;
+            }
+
+            match self.check_syllable(body) {
+                Ok(spelled) => poj.push_str(&spelled),
+                Err((kind, suggestion)) => {
+                    issues.push(Issue {
+                        kind,
+                        start,
+                        end: start + body.chars().count(),
+                        text: body.to_string(),
+                        suggestion: suggestion.map(|s| match_case(&s, body)),
+                    });
+                    ok = false;
+                },
+            }
+        }
+
+        if token.text.ends_with('-') {
+            poj.push('-');
+        }
+
+        ok.then_some(poj)
+    }
+
+    /// The POJ spelling of a correct syllable, or the issue with it
+    fn check_syllable(
+        &self,
+        syl: &str,
+    ) -> Result<String, (IssueKind, Option<String>)> {
+        let (body, tone) = strip_tone_diacritic(syl);
+        let poj = to_poj(&body);
+
+        if !self.is_legal(&poj) {
+            let suggestion = self.nearest_syllable(&poj, tone);
+            return Err((IssueKind::IllegalSyllable, suggestion));
+        }
+
+        if let Some(actual) = tone_mark_position(syl, tone) {
+            let tai_lo = poj != body.to_lowercase();
+            let expected = if tai_lo {
+                tai_lo_tone_position(&body)
+            } else {
+                get_tone_position(&body)
+            };
+            let allowed =
+                [get_tone_position(&body), tai_lo_tone_position(&body)];
+            if !allowed.contains(&Some(actual)) {
+                let placed = place_tone(&body, tone, expected);
+                return Err((IssueKind::MisplacedTone, Some(placed)));
+            }
+        }
+
+        Ok(place_tone(&poj, tone, get_tone_position(&poj)))
+    }
+
+    /// In the syllable list, or at least shaped like a syllable
+    fn is_legal(&self, poj: &str) -> bool {
+        is_legal_lomaji(poj)
+            || syllable_to_key_sequences(poj)
+                .iter()
+                .any(|key| self.syllable_trie.is_valid_syllable(key))
+    }
+
+    /// The closest syllable in the list, within two edits, with `tone`
+    fn nearest_syllable(&self, poj: &str, tone: Tone) -> Option<String> {
+        let query = syllable_to_key_sequences(poj).into_iter().next()?;
+        let mut best: Option<(usize, &str)> = None;
+
+        for (key, syl) in self.syllables.iter() {
+            let distance = *prefix_distances(key, &query, 2).last()?;
+            if distance <= 2 && best.is_none_or(|(d, _)| distance < d) {
+                best = Some((distance, syl));
+            }
+        }
+
+        best.map(|(_, syl)| {
+            let body: String = syl.nfd().collect();
+            place_tone(&body, tone, get_tone_position(&body))
+        })
+    }
+}
+
+/// A run of lomaji letters and hyphens
+struct Token {
+    /// Offsets in chars
+    start: usize,
+    end: usize,
+    text: String,
+    /// Directly after a single space that follows another token
+    after_space: bool,
+}
+
+struct TokenSyllable {
+    /// Offset in the token, in chars
+    offset: usize,
+    /// Hyphens before the syllable
+    hyphens: usize,
+    text: String,
+}
+
+impl Token {
+    fn syllables(&self) -> Vec<TokenSyllable> {
+        let mut ret = Vec::new();
+        let mut hyphens = 0;
+        let mut syl: Option<TokenSyllable> = None;
+
+        for (i, ch) in self.text.chars().enumerate() {
+            if ch == '-' {
+                if let Some(s) = syl.take() {
+                    ret.push(s);
+                }
+                hyphens += 1;
+                continue;
+            }
+            syl.get_or_insert_with(|| TokenSyllable {
+                offset: i,
+                hyphens: std::mem::take(&mut hyphens),
+                text: String::new(),
+            })
+            .text
+            .push(ch);
+        }
+        ret.extend(syl);
+
+        ret
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut token: Option<Token> = None;
+    let mut prev = None;
+    let mut prev_end = None;
+
+    for (i, ch) in text.chars().chain(std::iter::once(' ')).enumerate() {
+        if ch == '-' || is_lomaji_char(ch) {
+            token
+                .get_or_insert_with(|| Token {
+                    start: i,
+                    end: i,
+                    text: String::new(),
+                    after_space: prev == Some(' ')
+                        && prev_end.is_some_and(|end| end + 1 == i),
+                })
+                .text
+                .push(ch);
+        } else if let Some(mut t) = token.take() {
+            t.end = i;
+            prev_end = Some(i);
+            if t.text.chars().any(char::is_alphabetic) {
+                tokens.push(t);
+            }
+        }
+        prev = Some(ch);
+    }
+
+    tokens
+}
+
+fn is_lomaji_char(ch: char) -> bool {
+    (ch.is_alphabetic() && ch < HANJI_CUTOFF)
+        || ('\u{0300}'..='\u{036f}').contains(&ch)
+        || ch == DOT_KHIN
+}
+
+/// Lowercase POJ letters for a toneless syllable, which may be in Tâi-lô
+fn to_poj(body: &str) -> String {
+    let mut ret = body.to_lowercase();
+
+    if let Some(rest) = ret.strip_prefix("tsh") {
+        ret = format!("chh{}", rest);
+    } else if let Some(rest) = ret.strip_prefix("ts") {
+        ret = format!("ch{}", rest);
+    }
+
+    ret = ret
+        .replace("oo", &format!("o{}", DOT_ABOVE_RIGHT))
+        .replace("ua", "oa")
+        .replace("ue", "oe");
+
+    for (from, to) in [("ing", "eng"), ("ik", "ek")] {
+        if let Some(stem) = ret.strip_suffix(from) {
+            ret = format!("{}{}", stem, to);
+        }
+    }
+
+    if let Some(stem) = ret.strip_suffix("nnh") {
+        ret = format!("{}h{}", stem, NASAL_LC);
+    } else if let Some(stem) = ret.strip_suffix("nn") {
+        ret = format!("{}{}", stem, NASAL_LC);
+    }
+
+    ret
+}
+
+/// Byte offset of the letter carrying the tone mark of `syl`, in its NFD
+/// form without the mark
+fn tone_mark_position(syl: &str, tone: Tone) -> Option<usize> {
+    let mark = tone_to_char(&tone)?;
+    let nfd: String = syl.nfd().collect();
+    let at = nfd.find(mark)?;
+    nfd[..at]
+        .char_indices()
+        .rev()
+        .find(|(_, ch)| !('\u{0300}'..='\u{036f}').contains(ch))
+        .map(|(i, _)| i)
+}
+
+/// Tâi-lô puts the tone mark on a, then o, then e, and on the second vowel
+/// of iu and ui
+fn tai_lo_tone_position(body: &str) -> Option<usize> {
+    let lower = body.to_lowercase();
+    if !lower.contains(['a', 'o', 'e']) {
+        if let Some(at) = lower.find("iu").or_else(|| lower.find("ui")) {
+            return Some(at + 1);
+        }
+    }
+    ['a', 'o', 'e', 'u', 'i', 'n', 'm']
+        .iter()
+        .find_map(|&letter| lower.find(letter))
+}
+
+fn place_tone(body: &str, tone: Tone, at: Option<usize>) -> String {
+    let mut ret = body.to_string();
+    if let (Some(mark), Some(at)) = (tone_to_char(&tone), at) {
+        let len = ret[at..].chars().next().map_or(0, char::len_utf8);
+        ret.insert(at + len, mark);
+    }
+    normalize(&ret, Normalization::FontSafe)
+}
+
+fn letters(word: &str) -> String {
+    word.chars()
+        .filter(|&ch| ch != '-' && ch != ' ' && ch != DOT_KHIN)
+        .collect()
+}
+
+fn toneless(word: &str) -> String {
+    let letters = letters(word);
+    strip_tone_diacritic(&letters).0.nfc().collect()
+}
+
+/// Capitalizes `suggestion` if `original` is capitalized
+fn match_case(suggestion: &str, original: &str) -> String {
+    let mut chars = suggestion.chars();
+    match chars.next() {
+        Some(first) if original.starts_with(char::is_uppercase) => {
+            first.to_uppercase().chain(chars).collect()
+        },
+        _ => suggestion.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::*;
+
+    fn checker() -> SpellChecker {
+        let db = Database::new(debug_db_path()).unwrap();
+        SpellChecker::new(&db).unwrap()
+    }
+
+    fn issue(
+        checker: &SpellChecker,
+        text: &str,
+    ) -> (IssueKind, String, Option<String>) {
+        let issues = checker.check(text);
+        assert_eq!(issues.len(), 1, "{}: {:?}", text, issues);
+        let issue = issues.into_iter().next().unwrap();
+        (issue.kind, issue.text, issue.suggestion)
+    }
+
+    #[test]
+    fn it_accepts_correct_text() {
+        let checker = checker();
+        for text in ["Tâi-gí", "chia̍h-mi̍h, 食飯", "khòaⁿ--kìⁿ", "tsia̍h"]
+        {
+            assert!(checker.check(text).is_empty(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn it_finds_illegal_syllables() {
+        let checker = checker();
+        let (kind, text, suggestion) = issue(&checker, "lí chhiaq");
+        assert_eq!(kind, IssueKind::IllegalSyllable);
+        assert_eq!(text, "chhiaq");
+        assert!(suggestion.is_some());
+    }
+
+    #[test]
+    fn it_finds_misplaced_tone_marks() {
+        let checker = checker();
+        let res = issue(&checker, "kòan");
+        assert_eq!(res.0, IssueKind::MisplacedTone);
+        assert_eq!(res.2.as_deref(), Some("koàn"));
+
+        let res = issue(&checker, "Hoé");
+        assert_eq!(res.2.as_deref(), Some("Hóe"));
+    }
+
+    #[test]
+    fn it_finds_unknown_words() {
+        let checker = checker();
+        let res = issue(&checker, "Tâi-gi");
+        assert_eq!(res.0, IssueKind::UnknownWord);
+        assert_eq!(res.2.as_deref(), Some("Tâi-gí"));
+    }
+
+    #[test]
+    fn it_finds_inconsistent_hyphenation() {
+        let checker = checker();
+        let res = issue(&checker, "chia̍h si̍t");
+        assert_eq!(res.0, IssueKind::Hyphenation);
+        assert_eq!(res.2.as_deref(), Some("chia̍h-si̍t"));
+
+        let res = issue(&checker, "cho̍h-ji̍t");
+        assert_eq!(res.0, IssueKind::Hyphenation);
+        assert_eq!(res.2.as_deref(), Some("cho̍h-·ji̍t"));
+
+        let issues = checker.check("khòaⁿ---kìⁿ");
+        assert_eq!(issues[0].kind, IssueKind::Hyphenation);
+        assert_eq!((issues[0].start, issues[0].end), (5, 8));
+    }
+}
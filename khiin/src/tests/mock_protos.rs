@@ -1,5 +1,8 @@
 use khiin_protos::command::preedit::*;
 use khiin_protos::command::*;
+use khiin_protos::config::AppConfig;
+use khiin_protos::config::BoolValue;
+use protobuf::MessageField;
 
 pub fn mock_send_key_request(ch: char) -> Request {
     let mut req = Request::default();
@@ -11,6 +14,39 @@ pub fn mock_send_key_request(ch: char) -> Request {
     req
 }
 
+/// A `CMD_SET_CONFIG` request with the `AppConfig` filled in by `f`
+pub fn mock_set_config_request(f: impl FnOnce(&mut AppConfig)) -> Request {
+    let mut req = Request::default();
+    req.type_ = CommandType::CMD_SET_CONFIG.into();
+
+    let mut config = AppConfig::default();
+    f(&mut config);
+    req.config = Some(config).into();
+    req
+}
+
+pub fn mock_bool(value: bool) -> MessageField<BoolValue> {
+    Some(BoolValue {
+        value,
+        ..Default::default()
+    })
+    .into()
+}
+
+pub fn mock_select_candidate_request(id: i32) -> Request {
+    let mut req = Request::default();
+    req.type_ = CommandType::CMD_SELECT_CANDIDATE.into();
+    req.candidate_id = id;
+    req
+}
+
+pub fn mock_focus_candidate_request(id: i32) -> Request {
+    let mut req = Request::default();
+    req.type_ = CommandType::CMD_FOCUS_CANDIDATE.into();
+    req.candidate_id = id;
+    req
+}
+
 fn mock_command(cmd: &mut Command) {
     let mut cand = Candidate::new();
     cand.id = 1;
@@ -14,6 +14,8 @@ use crate::buffer::BufferMgr;
 use crate::config::Config;
 use crate::config::ToneMode;
 use crate::data::Dictionary;
+use crate::data::HanjiVariants;
+use crate::data::Snippets;
 use crate::db::models::InputType;
 use crate::db::models::KeyConversion;
 use crate::db::Database;
@@ -60,6 +62,10 @@ pub fn mock_conversion(input: &str, output: &str) -> KeyConversion {
         khin_ok: true,
         khinless_ok: true,
         annotation: None,
+        gloss: None,
+        variant: false,
+        fuzzy: false,
+        predicted: false,
     }
 }
 
@@ -69,6 +75,9 @@ pub(crate) fn test_harness() -> (EngInner, BufferMgr) {
             db: get_db(),
             dict: get_dict(),
             conf: get_conf(),
+            snapshot: None,
+            variants: HanjiVariants::default(),
+            snippets: Snippets::default(),
         },
         BufferMgr::new(),
     )
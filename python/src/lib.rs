@@ -0,0 +1,324 @@
+use khiin::Engine as KhiinEngine;
+use khiin_protos::command::*;
+use khiin_protos::config::AppConfig;
+use khiin_protos::config::AppInputMode;
+use khiin_protos::config::AppKhinMode;
+use khiin_protos::config::AppOutputMode;
+use khiin_protos::config::BoolValue;
+use khiin_protos::config::KeyConfiguration;
+use protobuf::Message;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+fn runtime_err(e: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+/// The parts of an engine `Response` that are useful for scripting.
+#[pyclass]
+#[derive(Clone, Debug, Default)]
+pub struct Response {
+    #[pyo3(get)]
+    preedit: String,
+    #[pyo3(get)]
+    candidates: Vec<String>,
+    #[pyo3(get)]
+    focused: i32,
+    #[pyo3(get)]
+    committed: bool,
+    #[pyo3(get)]
+    committed_text: String,
+}
+
+#[pymethods]
+impl Response {
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl From<&khiin_protos::command::Response> for Response {
+    fn from(res: &khiin_protos::command::Response) -> Self {
+        let preedit = res
+            .preedit
+            .segments
+            .iter()
+            .map(|s| s.value.as_str())
+            .collect::<String>();
+
+        let candidates = res
+            .candidate_list
+            .candidates
+            .iter()
+            .map(|c| c.value.clone())
+            .collect();
+
+        Response {
+            preedit,
+            candidates,
+            focused: res.candidate_list.focused,
+            committed: res.committed,
+            committed_text: res.committed_text.clone(),
+        }
+    }
+}
+
+/// Engine instance that simulates key presses, as an IME client would.
+#[pyclass(unsendable)]
+pub struct Engine {
+    engine: KhiinEngine,
+}
+
+#[pymethods]
+impl Engine {
+    #[new]
+    fn new(db_path: &str) -> PyResult<Self> {
+        KhiinEngine::new(db_path)
+            .map(|engine| Engine { engine })
+            .ok_or_else(|| runtime_err(format!("Unable to load {}", db_path)))
+    }
+
+    /// Sends a single key. Space, `\n` and `\b` are sent as the space, enter
+    /// and backspace keys.
+    fn send_key(&mut self, key: char) -> PyResult<Response> {
+        self.send(key_request(key))
+    }
+
+    /// Sends each character of `keys` in turn and returns the last response.
+    fn send_keys(&mut self, keys: &str) -> PyResult<Response> {
+        let mut res = Response::default();
+        for key in keys.chars() {
+            res = self.send_key(key)?;
+        }
+        Ok(res)
+    }
+
+    /// Commits the current composition.
+    fn commit(&mut self) -> PyResult<Response> {
+        self.send(request(CommandType::CMD_COMMIT))
+    }
+
+    /// Clears the current composition.
+    fn reset(&mut self) -> PyResult<()> {
+        self.send(request(CommandType::CMD_RESET))?;
+        Ok(())
+    }
+
+    #[pyo3(signature = (
+        input_mode = "manual",
+        output_mode = "lomaji",
+        tone_mode = "telex",
+        khin_mode = "hyphen",
+    ))]
+    fn set_config(
+        &mut self,
+        input_mode: &str,
+        output_mode: &str,
+        tone_mode: &str,
+        khin_mode: &str,
+    ) -> PyResult<()> {
+        let mut config = AppConfig::new();
+        config.input_mode = match input_mode {
+            "continuous" => AppInputMode::CONTINUOUS,
+            "classic" => AppInputMode::CLASSIC,
+            "manual" => AppInputMode::MANUAL,
+            _ => return Err(invalid("input_mode", input_mode)),
+        }
+        .into();
+        config.output_mode = match output_mode {
+            "lomaji" => AppOutputMode::LOMAJI,
+            "hanji" => AppOutputMode::HANJI,
+            "tps" => AppOutputMode::TPS,
+            "ascii" => AppOutputMode::ASCII,
+            _ => return Err(invalid("output_mode", output_mode)),
+        }
+        .into();
+        config.khin_mode = match khin_mode {
+            "khinless" => AppKhinMode::KHINLESS,
+            "hyphen" => AppKhinMode::HYPHEN,
+            "dot" => AppKhinMode::DOT,
+            _ => return Err(invalid("khin_mode", khin_mode)),
+        }
+        .into();
+        let (telex, digits) = match tone_mode {
+            "telex" => (true, false),
+            "hybrid" => (true, true),
+            "numeric" => (false, false),
+            _ => return Err(invalid("tone_mode", tone_mode)),
+        };
+        let mut telex_enabled = BoolValue::new();
+        telex_enabled.value = telex;
+        config.telex_enabled = Some(telex_enabled).into();
+        let mut use_digits_as_fallback = BoolValue::new();
+        use_digits_as_fallback.value = digits;
+        let mut key_config = KeyConfiguration::new();
+        key_config.use_digits_as_fallback = Some(use_digits_as_fallback).into();
+        config.key_config = Some(key_config).into();
+
+        let mut req = request(CommandType::CMD_SET_CONFIG);
+        req.config = Some(config).into();
+        self.send(req)?;
+        Ok(())
+    }
+
+    /// Converts a whole sentence in one pass, without touching the buffer.
+    fn convert(&self, text: &str) -> PyResult<String> {
+        self.engine.convert(text).map_err(runtime_err)
+    }
+
+    /// Splits `text` into the most likely sequence of dictionary words.
+    fn segment(&self, text: &str) -> PyResult<Vec<String>> {
+        self.engine.segment(text).map_err(runtime_err)
+    }
+}
+
+impl Engine {
+    fn send(&mut self, req: Request) -> PyResult<Response> {
+        let mut cmd = Command::new();
+        cmd.request = Some(req).into();
+        let bytes = cmd.write_to_bytes().map_err(runtime_err)?;
+        let bytes = self
+            .engine
+            .send_command_bytes(&bytes)
+            .map_err(runtime_err)?;
+        let cmd = Command::parse_from_bytes(&bytes).map_err(runtime_err)?;
+
+        if cmd.response.error.enum_value_or_default() != ErrorCode::OK {
+            return Err(runtime_err("Engine returned an error"));
+        }
+
+        Ok(Response::from(&*cmd.response))
+    }
+}
+
+fn invalid(name: &str, value: &str) -> PyErr {
+    PyValueError::new_err(format!("Invalid {}: {}", name, value))
+}
+
+fn request(ty: CommandType) -> Request {
+    let mut req = Request::new();
+    req.type_ = ty.into();
+    req
+}
+
+fn key_request(key: char) -> Request {
+    let mut ke = KeyEvent::new();
+    match key {
+        ' ' => ke.special_key = SpecialKey::SK_SPACE.into(),
+        '\n' => ke.special_key = SpecialKey::SK_ENTER.into(),
+        '\x08' => ke.special_key = SpecialKey::SK_BACKSPACE.into(),
+        _ => ke.key_code = key as i32,
+    }
+
+    let mut req = request(CommandType::CMD_SEND_KEY);
+    req.key_event = Some(ke).into();
+    req
+}
+
+/// Whether `text` is made up of legal lomaji syllables.
+#[pyfunction]
+fn is_legal_lomaji(text: &str) -> bool {
+    khiin_ji::is_legal_lomaji(text)
+}
+
+/// Whether `text` contains any Hanji.
+#[pyfunction]
+fn contains_hanji(text: &str) -> bool {
+    khiin_ji::contains_hanji(text)
+}
+
+/// Removes the tone diacritic, returning the bare syllable and tone number.
+#[pyfunction]
+fn strip_tone_diacritic(syllable: &str) -> (String, i32) {
+    let (stripped, tone) = khiin_ji::strip_tone_diacritic(syllable);
+    (stripped, tone as i32)
+}
+
+/// Removes a leading khin dot or double hyphen.
+#[pyfunction]
+fn strip_khin(syllable: &str) -> (String, bool) {
+    let mut syllable = syllable.to_string();
+    let stripped = khiin_ji::strip_khin(&mut syllable);
+    (syllable, stripped)
+}
+
+/// The combining diacritic for a tone number, if it has one.
+#[pyfunction]
+fn tone_to_char(tone: i32) -> Option<char> {
+    khiin_ji::tone_to_char(&tone.into())
+}
+
+/// The tone number for a numeric tone key.
+#[pyfunction]
+fn key_to_tone(key: char) -> i32 {
+    khiin_ji::key_to_tone(key) as i32
+}
+
+/// Whether the syllable has a letter that can carry a tone mark.
+#[pyfunction]
+fn has_tone_letter(syllable: &str) -> bool {
+    khiin_ji::has_tone_letter(syllable)
+}
+
+/// Keys for a POJ syllable as `(numeric, telex, toneless)`.
+#[pyfunction]
+fn poj_syl_to_key_sequences(syllable: &str) -> (String, String, String) {
+    khiin_ji::poj_syl_to_key_sequences(syllable)
+}
+
+/// All key sequences that can be typed to produce `syllable`.
+#[pyfunction]
+fn syllable_to_key_sequences(syllable: &str) -> Vec<String> {
+    khiin_ji::syllable_to_key_sequences(syllable)
+}
+
+#[pymodule]
+#[pyo3(name = "khiin")]
+fn khiin_module(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Engine>()?;
+    m.add_class::<Response>()?;
+    m.add_function(wrap_pyfunction!(is_legal_lomaji, m)?)?;
+    m.add_function(wrap_pyfunction!(contains_hanji, m)?)?;
+    m.add_function(wrap_pyfunction!(strip_tone_diacritic, m)?)?;
+    m.add_function(wrap_pyfunction!(strip_khin, m)?)?;
+    m.add_function(wrap_pyfunction!(tone_to_char, m)?)?;
+    m.add_function(wrap_pyfunction!(key_to_tone, m)?)?;
+    m.add_function(wrap_pyfunction!(has_tone_letter, m)?)?;
+    m.add_function(wrap_pyfunction!(poj_syl_to_key_sequences, m)?)?;
+    m.add_function(wrap_pyfunction!(syllable_to_key_sequences, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_engine() -> Engine {
+        let path = std::env::current_dir()
+            .unwrap()
+            .join("../target/debug/khiin.db");
+        Engine::new(path.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn it_simulates_key_presses() {
+        let mut engine = get_engine();
+        engine
+            .set_config("classic", "hanji", "numeric", "hyphen")
+            .unwrap();
+        let res = engine.send_keys("a").unwrap();
+        assert_eq!(res.preedit, "a");
+        assert!(!res.candidates.is_empty());
+
+        let res = engine.commit().unwrap();
+        assert!(res.committed);
+    }
+
+    #[test]
+    fn it_wraps_tone_helpers() {
+        assert_eq!(strip_tone_diacritic("hó"), ("ho".to_string(), 2));
+        assert_eq!(key_to_tone('5'), 5);
+        assert_eq!(strip_khin("--a"), ("a".to_string(), true));
+    }
+}
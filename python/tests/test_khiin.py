@@ -0,0 +1,23 @@
+# Smoke tests for the wheel. Build and install with `maturin develop`, then
+# run `pytest python/tests` from the repository root.
+import pathlib
+
+import khiin
+
+DB_PATH = pathlib.Path(__file__).parents[2] / "target" / "debug" / "khiin.db"
+
+
+def test_tone_helpers():
+    assert khiin.strip_tone_diacritic("hó") == ("ho", 2)
+    assert khiin.key_to_tone("5") == 5
+    assert khiin.is_legal_lomaji("chhiau")
+    assert khiin.contains_hanji("台語")
+
+
+def test_engine():
+    engine = khiin.Engine(str(DB_PATH))
+    engine.set_config(input_mode="classic", tone_mode="numeric")
+    res = engine.send_keys("a")
+    assert res.preedit == "a"
+    assert res.candidates
+    assert engine.segment("goa") == ["goa"]
@@ -9,14 +9,19 @@ struct CandidateView: View {
         let candidates = candList.candidates
         let focused = Int(candList.focused)
         let page = Int(candList.page)
+        let pageSize = candList.pageSize > 0 ? Int(candList.pageSize) : 9
 
-        let start = page * 9
-        let end = start + 9 > candidates.count ? candidates.count : start + 9
-        let focus = focused == -1 ? -1 : focused % 9
+        let start = page * pageSize
+        let end =
+            start + pageSize > candidates.count
+            ? candidates.count : start + pageSize
+        let focus = focused == -1 ? -1 : focused % pageSize
 
         ZStack {
             VStack(alignment: .leading, spacing: 0) {
-                ForEach(Array(zip(0...8, candidates[start..<end])), id: \.0) {
+                ForEach(
+                    Array(zip(0..<pageSize, candidates[start..<end])), id: \.0
+                ) {
                     index, candidate in
 
                     CandidateItem(
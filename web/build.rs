@@ -0,0 +1,21 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=WASI_SYSROOT");
+
+    // The bundled SQLite is C code, so on wasm32-unknown-unknown it needs a
+    // libc (malloc, string functions, etc.). We link the one from wasi-libc.
+    let target = env::var("TARGET").unwrap();
+    if target == "wasm32-unknown-unknown" {
+        if let Ok(sysroot) = env::var("WASI_SYSROOT") {
+            println!(
+                "cargo:rustc-link-search=native={sysroot}/lib/wasm32-wasi"
+            );
+            println!("cargo:rustc-link-lib=static=c");
+        } else {
+            println!(
+                "cargo:warning=WASI_SYSROOT is not set, see web/README.md"
+            );
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! WebAssembly bindings for the engine, used by the online typing demo.
+//!
+//! The database is passed in as bytes (e.g. the result of `fetch("khiin.db")`)
+//! since there is no filesystem in the browser.
+
+#[cfg(any(target_arch = "wasm32", test))]
+mod vfs;
+
+use khiin::Engine;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct KhiinEngine {
+    engine: Engine,
+}
+
+#[wasm_bindgen]
+impl KhiinEngine {
+    /// Loads the engine from the contents of a `khiin.db` file.
+    #[wasm_bindgen(constructor)]
+    pub fn new(db_bytes: &[u8]) -> Result<KhiinEngine, JsError> {
+        Engine::from_bytes(db_bytes)
+            .map(|engine| KhiinEngine { engine })
+            .ok_or_else(|| JsError::new("Unable to load database"))
+    }
+
+    /// Sends a serialized `Command` protobuf and returns the serialized
+    /// `Command` with its `Response` attached.
+    #[wasm_bindgen(js_name = sendCommand)]
+    pub fn send_command(&mut self, bytes: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.engine
+            .send_command_bytes(bytes)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Converts a whole sentence in one pass.
+    pub fn convert(&self, text: &str) -> Result<String, JsError> {
+        self.engine
+            .convert(text)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Splits the input into dictionary words, separated by spaces.
+    pub fn segment(&self, text: &str) -> Result<String, JsError> {
+        self.engine
+            .segment(text)
+            .map(|words| words.join(" "))
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+}
@@ -0,0 +1,196 @@
+//! A memory-only SQLite VFS.
+//!
+//! SQLite is compiled with `SQLITE_OS_OTHER` for the browser, which leaves it
+//! without any VFS. The engine only ever uses in-memory databases (see
+//! `Database::from_bytes`), so this VFS refuses to open files and only
+//! provides the randomness, time and path hooks that SQLite needs to run.
+
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ptr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use rusqlite::ffi;
+
+pub(crate) const VFS_NAME: &[u8] = b"khiin-memory\0";
+
+const MAX_PATHNAME: c_int = 512;
+
+static SEED: AtomicU64 = AtomicU64::new(0x2545_f491_4f6c_dd1d);
+
+/// Registers the VFS with SQLite, optionally as the default VFS.
+pub(crate) fn register(make_default: bool) -> c_int {
+    let vfs = Box::new(ffi::sqlite3_vfs {
+        iVersion: 1,
+        szOsFile: std::mem::size_of::<ffi::sqlite3_file>() as c_int,
+        mxPathname: MAX_PATHNAME,
+        pNext: ptr::null_mut(),
+        zName: VFS_NAME.as_ptr() as *const c_char,
+        pAppData: ptr::null_mut(),
+        xOpen: Some(x_open),
+        xDelete: Some(x_delete),
+        xAccess: Some(x_access),
+        xFullPathname: Some(x_full_pathname),
+        xDlOpen: None,
+        xDlError: None,
+        xDlSym: None,
+        xDlClose: None,
+        xRandomness: Some(x_randomness),
+        xSleep: Some(x_sleep),
+        xCurrentTime: Some(x_current_time),
+        xGetLastError: None,
+        xCurrentTimeInt64: None,
+        xSetSystemCall: None,
+        xGetSystemCall: None,
+        xNextSystemCall: None,
+    });
+
+    // SQLite keeps the pointer for the lifetime of the process
+    unsafe { ffi::sqlite3_vfs_register(Box::leak(vfs), make_default as c_int) }
+}
+
+/// Called by SQLite during `sqlite3_initialize` when built with
+/// `SQLITE_OS_OTHER`.
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn sqlite3_os_init() -> c_int {
+    register(true)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn sqlite3_os_end() -> c_int {
+    ffi::SQLITE_OK
+}
+
+unsafe extern "C" fn x_open(
+    _vfs: *mut ffi::sqlite3_vfs,
+    _name: ffi::sqlite3_filename,
+    file: *mut ffi::sqlite3_file,
+    _flags: c_int,
+    _out_flags: *mut c_int,
+) -> c_int {
+    (*file).pMethods = ptr::null();
+    ffi::SQLITE_CANTOPEN
+}
+
+unsafe extern "C" fn x_delete(
+    _vfs: *mut ffi::sqlite3_vfs,
+    _name: *const c_char,
+    _sync_dir: c_int,
+) -> c_int {
+    ffi::SQLITE_IOERR_DELETE
+}
+
+unsafe extern "C" fn x_access(
+    _vfs: *mut ffi::sqlite3_vfs,
+    _name: *const c_char,
+    _flags: c_int,
+    res_out: *mut c_int,
+) -> c_int {
+    *res_out = 0;
+    ffi::SQLITE_OK
+}
+
+unsafe extern "C" fn x_full_pathname(
+    _vfs: *mut ffi::sqlite3_vfs,
+    name: *const c_char,
+    n_out: c_int,
+    out: *mut c_char,
+) -> c_int {
+    let len = std::ffi::CStr::from_ptr(name).to_bytes().len();
+    if len + 1 > n_out as usize {
+        return ffi::SQLITE_CANTOPEN;
+    }
+    ptr::copy_nonoverlapping(name, out, len + 1);
+    ffi::SQLITE_OK
+}
+
+unsafe extern "C" fn x_randomness(
+    _vfs: *mut ffi::sqlite3_vfs,
+    n_byte: c_int,
+    out: *mut c_char,
+) -> c_int {
+    // xorshift64*, only used for temporary names and random rowids
+    let mut x = SEED.load(Ordering::Relaxed) ^ now_millis().to_bits();
+    for i in 0..n_byte as usize {
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        *out.add(i) = (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 56) as c_char;
+    }
+    SEED.store(x, Ordering::Relaxed);
+    n_byte
+}
+
+unsafe extern "C" fn x_sleep(
+    _vfs: *mut ffi::sqlite3_vfs,
+    _microseconds: c_int,
+) -> c_int {
+    0
+}
+
+unsafe extern "C" fn x_current_time(
+    _vfs: *mut ffi::sqlite3_vfs,
+    out: *mut f64,
+) -> c_int {
+    // Julian day number
+    *out = 2440587.5 + now_millis() / 86_400_000.0;
+    ffi::SQLITE_OK
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now_millis() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_millis() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as f64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+    use rusqlite::OpenFlags;
+
+    use super::*;
+
+    #[test]
+    fn it_runs_in_memory_databases() {
+        assert_eq!(register(false), ffi::SQLITE_OK);
+        let conn = Connection::open_with_flags_and_vfs(
+            ":memory:",
+            OpenFlags::default(),
+            "khiin-memory",
+        )
+        .unwrap();
+        conn.execute_batch(
+            "create table t (x integer); insert into t values (1), (2);",
+        )
+        .unwrap();
+        let sum: i64 = conn
+            .query_row("select sum(x) from t", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(sum, 3);
+        let now: f64 = conn
+            .query_row("select julianday('now')", [], |r| r.get(0))
+            .unwrap();
+        assert!(now > 2460000.0);
+    }
+
+    #[test]
+    fn it_does_not_open_files() {
+        register(false);
+        let res = Connection::open_with_flags_and_vfs(
+            "khiin.db",
+            OpenFlags::default(),
+            "khiin-memory",
+        );
+        assert!(res.is_err());
+    }
+}
@@ -162,6 +162,14 @@ impl Pager {
     fn max_col_size(&self) -> usize {
         match &*self.display_mode.borrow() {
             DisplayMode::ShortColumn => SHORT_COL_SIZE,
+            _ => self.page_size(),
+        }
+    }
+
+    /// The page size used by the engine, so digit keys match what is shown
+    fn page_size(&self) -> usize {
+        match self.command.response.candidate_list.page_size {
+            size if size > 0 => size as usize,
             _ => LONG_COL_SIZE,
         }
     }